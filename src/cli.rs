@@ -0,0 +1,2677 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::Read;
+use std::mem;
+use std::path::Path;
+
+use anyhow::{Context, Error};
+use chrono::{Date, Datelike, DateTime, Duration, MAX_DATE, MIN_DATE, Utc};
+use clap::{App, ArgMatches, Shell};
+use clipboard::{ClipboardContext, ClipboardProvider};
+use console::style;
+use csv;
+use git2::{Cred, CredentialType, PushOptions, RemoteCallbacks, Repository, Signature};
+use path_abs::{FileWrite, PathDir, PathFile, PathInfo};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use regex::Regex;
+use serde_json;
+use termion::event::Key;
+use termion::input::MouseTerminal;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use tui::backend::TermionBackend;
+use tui::layout::{Alignment, Constraint, Direction, Layout};
+use tui::style::{Color, Modifier, Style};
+use tui::Terminal;
+use tui::widgets::{BarChart, Block, Borders, Paragraph, Row, Table, Text, Widget};
+use unicode_width::UnicodeWidthStr;
+
+use quoth::config;
+use quoth::errors::QuothError;
+use quoth::quoth::database::Trees;
+use quoth::quoth::quotes::{PrintStyle, Quote, TSVQuote};
+use quoth::quoth::stats::{AuthorCounts, Stats};
+use quoth::quoth::theme::{RoleStyle, Theme};
+use quoth::quoth::{change_quoth_dir, get_profile_dir, get_quoth_dir, get_setting, list_profiles};
+use quoth::utils;
+
+use crate::events::{self, Event, Events};
+
+/// Parses the maximum number of quotes to display from command-line arguments
+fn get_limit(matches: &ArgMatches<'_>) -> Result<Option<usize>, Error> {
+    Ok(utils::get_argument_value("limit", matches)?
+        .map(|limit| limit.parse::<usize>())
+        .transpose()
+        .with_context(|| "Given limit is not a number")?)
+}
+
+/// Whether `list`/`search` output goes through the pager: with `--pager`, or by default when printing to a terminal
+fn use_pager(matches: &ArgMatches<'_>) -> bool {
+    !matches.is_present("no-pager")
+        && (matches.is_present("pager") || termion::is_tty(&io::stdout()))
+}
+
+/// Reads the key that quits the TUIs and their tick rate from command-line arguments,
+/// falling back to the settings in the config file and then to the defaults
+fn get_events_config(matches: &ArgMatches<'_>) -> Result<events::Config, Error> {
+    let mut events_config = events::Config::default();
+    let exit_key = match utils::get_argument_value("exit-key", matches)? {
+        Some(exit_key) => Some(exit_key.to_owned()),
+        None => get_setting(config::EXIT_KEY_SETTING)?,
+    };
+    if let Some(exit_key) = exit_key {
+        events_config.exit_key = events::parse_key(&exit_key)?;
+    }
+    let tick_rate = match utils::get_argument_value("tick-rate", matches)? {
+        Some(tick_rate) => Some(tick_rate.to_owned()),
+        None => get_setting(config::TICK_RATE_SETTING)?,
+    };
+    if let Some(tick_rate) = tick_rate {
+        let millis = tick_rate
+            .parse::<u64>()
+            .ok()
+            .filter(|millis| *millis > 0)
+            .ok_or_else(|| QuothError::BadSetting {
+                name: config::TICK_RATE_SETTING.into(),
+                value: tick_rate.clone(),
+            })?;
+        events_config.tick_rate = std::time::Duration::from_millis(millis);
+    }
+    Ok(events_config)
+}
+
+/// Reads the width quotes are laid out for from `--width`, falling back to the setting in the config file
+/// and then to the width of the terminal
+fn get_width(matches: &ArgMatches<'_>) -> Result<usize, Error> {
+    let width = match utils::get_argument_value("width", matches)? {
+        Some(width) => Some(width.to_owned()),
+        None => get_setting(config::WIDTH_SETTING)?,
+    };
+    match width {
+        Some(width) => Ok(width
+            .parse::<usize>()
+            .ok()
+            .filter(|width| *width >= config::MIN_TERMINAL_WIDTH)
+            .ok_or_else(|| QuothError::BadSetting {
+                name: config::WIDTH_SETTING.into(),
+                value: width.clone(),
+            })?),
+        None => Ok(utils::terminal_width()),
+    }
+}
+
+/// Reads the symbol shown above quotes from `--symbol`, falling back to the setting in the config file
+/// and then to `utils::RAVEN` (an empty symbol leaves a blank line)
+fn get_symbol(matches: &ArgMatches<'_>) -> Result<String, Error> {
+    Ok(match matches.value_of("symbol") {
+        Some(symbol) => symbol.trim().to_owned(),
+        None => get_setting(config::SYMBOL_SETTING)?.unwrap_or_else(|| utils::RAVEN.to_string()),
+    })
+}
+
+/// Reads the built-in theme named in the config file (the default theme if there isn't one),
+/// with its styles replaced by any `color.<role>` settings
+fn get_theme() -> Result<Theme, Error> {
+    let mut theme = match get_setting(config::THEME_SETTING)? {
+        Some(name) => Theme::named(&name).ok_or_else(|| QuothError::BadSetting {
+            name: config::THEME_SETTING.into(),
+            value: name.clone(),
+        })?,
+        None => Theme::default(),
+    };
+    for role in Theme::ROLES.iter() {
+        let name = format!("{}{}", config::COLOR_SETTING_PREFIX, role);
+        if let Some(value) = get_setting(&name)? {
+            *theme.role_mut(role).unwrap() =
+                RoleStyle::parse(&value).ok_or(QuothError::BadSetting { name, value })?;
+        }
+    }
+    Ok(theme)
+}
+
+/// Reads whether tags are lowercased from the config file (they are unless it has `lowercase-tags = false`)
+fn get_lowercase_tags() -> Result<bool, Error> {
+    match get_setting(config::LOWERCASE_TAGS_SETTING)? {
+        Some(value) => Ok(value.parse::<bool>().map_err(|_| QuothError::BadSetting {
+            name: config::LOWERCASE_TAGS_SETTING.into(),
+            value,
+        })?),
+        None => Ok(true),
+    }
+}
+
+/// `tui` equivalent of a theme's style (`console`'s white is `tui`'s gray)
+fn tui_style(role_style: &RoleStyle) -> Style {
+    let mut style = Style::default();
+    if let Some(color) = role_style.color {
+        style = style.fg(match color {
+            console::Color::Black => Color::Black,
+            console::Color::Red => Color::Red,
+            console::Color::Green => Color::Green,
+            console::Color::Yellow => Color::Yellow,
+            console::Color::Blue => Color::Blue,
+            console::Color::Magenta => Color::Magenta,
+            console::Color::Cyan => Color::Cyan,
+            console::Color::White => Color::Gray,
+        });
+    }
+    let mut modifier = Modifier::empty();
+    for attribute in &role_style.attributes {
+        modifier |= match attribute {
+            console::Attribute::Bold => Modifier::BOLD,
+            console::Attribute::Dim => Modifier::DIM,
+            console::Attribute::Italic => Modifier::ITALIC,
+            console::Attribute::Underlined => Modifier::UNDERLINED,
+            _ => Modifier::empty(),
+        };
+    }
+    style.modifier(modifier)
+}
+
+/// Writes quotes as TSV (dates in `date_format`), JSON, BibTeX, iCalendar, or an RSS feed titled `feed_title` (`format`),
+/// leaving out the TSV header or the separator before the first BibTeX entry if `appending` to existing content
+fn write_quotes(
+    quotes: impl Iterator<Item = Result<Quote, Error>>,
+    mut writer: Box<dyn io::Write>,
+    format: &str,
+    date_format: &str,
+    feed_title: &str,
+    appending: bool,
+) -> Result<(), Error> {
+    match format {
+        "json" => Quote::write_json(quotes, writer),
+        "ical" | "rss" if appending => Err(QuothError::DoingNothing {
+            message: format!(
+                "Quotes can't be added to the end of an existing {} file.",
+                format
+            ),
+        }
+        .into()),
+        "ical" => Quote::write_ical(quotes, writer),
+        "rss" => Quote::write_rss(quotes, writer, feed_title),
+        "bibtex" => {
+            for (i, quote) in quotes.enumerate() {
+                if i > 0 || appending {
+                    writer.write_all(b"\n")?;
+                }
+                writer.write_all(quote?.to_bibtex().as_bytes())?;
+            }
+            writer.flush()?;
+            Ok(())
+        }
+        _ => {
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(b'\t')
+                .has_headers(!appending)
+                .from_writer(writer);
+            for quote in quotes {
+                writer.serialize(TSVQuote::from_quote(quote?, date_format))?;
+            }
+            writer.flush()?;
+            Ok(())
+        }
+    }
+}
+
+/// Parses a TSV/CSV record into a quote, with `header_indices` mapping each column to a quote field
+/// (book, author, tags, date, quote, page, note, rating, source) or None for columns quoth doesn't use
+fn parse_delimited_record(
+    record: csv::Result<csv::StringRecord>,
+    header_indices: &[Option<&i32>],
+) -> Result<Quote, Error> {
+    let mut quote_data = (
+        "",
+        "",
+        "",
+        Utc::now(),
+        String::new(),
+        None,
+        String::new(),
+        None,
+        None,
+    );
+    let record = record?;
+    for (entry, index) in record.into_iter().zip(header_indices.iter()) {
+        if let Some(i) = index {
+            match i {
+                0 => quote_data.0 = entry,
+                1 => quote_data.1 = entry,
+                2 => quote_data.2 = entry,
+                3 => quote_data.3 = utils::parse_date(entry)?.and_hms(0, 0, 0),
+                4 => quote_data.4 = entry.into(),
+                5 => quote_data.5 = utils::parse_page(entry)?,
+                6 => quote_data.6 = entry.trim().into(),
+                7 => quote_data.7 = utils::parse_rating(entry)?,
+                8 => quote_data.8 = utils::parse_source(entry)?,
+                _ => {
+                    return Err(QuothError::OutOfCheeseError {
+                        message: "Please Reinstall Universe And Reboot".into(),
+                    }
+                    .into())
+                }
+            }
+        }
+    }
+    // The index is assigned when the quote is added
+    Ok(Quote {
+        page: quote_data.5,
+        note: if quote_data.6.is_empty() {
+            None
+        } else {
+            Some(quote_data.6)
+        },
+        rating: quote_data.7,
+        source: quote_data.8,
+        ..Quote::new(
+            0,
+            quote_data.0,
+            quote_data.1,
+            quote_data.2,
+            quote_data.3,
+            quote_data.4,
+        )
+    })
+}
+
+/// Regex for `quoth search`: the words of `pattern` in order (or any of them with `any`), or the pattern as it is if `literal`
+fn search_pattern(pattern: &str, literal: bool, any: bool) -> Result<Regex, Error> {
+    if literal {
+        return Ok(Regex::new(&format!(r"(?i){}", regex::escape(pattern)))?);
+    }
+    let separator = if any { "|" } else { ".+" };
+    Ok(Regex::new(&format!(
+        r"(?imxs){}",
+        pattern
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(separator)
+    ))
+    .map_err(|_| QuothError::BadPattern {
+        pattern: pattern.to_owned(),
+    })?)
+}
+
+/// Prints a quote prettily, or inside a box with `--box`
+fn print_quote(quote: &Quote, boxed: bool, print_style: &PrintStyle) {
+    if boxed {
+        print!("{}", quote.to_boxed(print_style))
+    } else {
+        quote.pretty_print_with(print_style)
+    }
+}
+
+/// Prints what `import --dry-run` would add: the number of quotes, distinct authors, books, and tags, and their date range,
+/// followed by the records that would be skipped because they couldn't be read
+fn print_import_summary(records: &[Result<Quote, Error>]) {
+    let quotes: Vec<_> = records
+        .iter()
+        .filter_map(|record| record.as_ref().ok())
+        .collect();
+    let authors: HashSet<_> = quotes.iter().flat_map(|quote| &quote.authors).collect();
+    let books: HashSet<_> = quotes.iter().map(|quote| &quote.book).collect();
+    let tags: HashSet<_> = quotes.iter().flat_map(|quote| &quote.tags).collect();
+    println!(
+        "Would import {} quotes ({} authors, {} books, {} tags), skipping {} records",
+        quotes.len(),
+        authors.len(),
+        books.len(),
+        tags.len(),
+        records.len() - quotes.len()
+    );
+    let dates = quotes.iter().map(|quote| quote.date.date());
+    if let (Some(first), Some(last)) = (dates.clone().min(), dates.max()) {
+        println!(
+            "Dated from {} to {}",
+            first.format("%Y-%m-%d"),
+            last.format("%Y-%m-%d")
+        );
+    }
+    print_skipped_records(
+        records
+            .iter()
+            .enumerate()
+            .filter_map(|(i, record)| record.as_ref().err().map(|error| (i + 1, error))),
+    );
+}
+
+/// Prints the number (counting from 1, after any header) and error of each record an import couldn't read
+fn print_skipped_records<'e>(skipped: impl IntoIterator<Item = (usize, &'e Error)>) {
+    for (number, error) in skipped {
+        println!("  Record {}: {}", number, error);
+    }
+}
+
+/// Stores
+/// - the location of the quoth directory
+/// - the location of the data of the profile in use (the quoth directory itself for the default profile)
+/// - argument parsing information from `clap`
+/// - the `sled` databases storing linkage information between authors, books, tags, and quotes
+pub struct Quoth<'a> {
+    quoth_dir: &'a PathDir,
+    profile_dir: &'a PathDir,
+    matches: ArgMatches<'a>,
+    trees: Trees,
+    /// Width and symbol quotes are printed with (see `get_width` and `get_symbol`)
+    print_style: PrintStyle,
+}
+
+/// Stores (author, book, tags, date) filters parsed from command-line arguments to restrict the quotes to look at
+struct Filters<'a> {
+    author: Option<&'a str>,
+    book: Option<&'a str>,
+    /// A quote must have all of these tags
+    tags: Vec<&'a str>,
+    /// A quote must have at least one of these tags
+    any_tags: Vec<&'a str>,
+    /// A quote must have none of these tags
+    not_tags: Vec<&'a str>,
+    /// A quote must be rated at least this
+    min_rating: Option<u8>,
+    from_date: Option<DateTime<Utc>>,
+    to_date: Option<DateTime<Utc>>,
+    /// A quote must have been added to quoth in this range (whatever its own date)
+    added_from_date: Option<DateTime<Utc>>,
+    added_to_date: Option<DateTime<Utc>>,
+}
+
+impl<'a> Filters<'a> {
+    /// Parses filters (on author, book, tags, date) from command-line arguments
+    fn get_filters(matches: &'a ArgMatches<'a>) -> Result<Filters<'a>, Error> {
+        let today = Utc::now().date();
+        let window_start = if matches.is_present("last-week") {
+            Some(today - Duration::weeks(1))
+        } else if matches.is_present("last-month") {
+            Some(utils::months_before(today, 1))
+        } else if matches.is_present("last-year") {
+            Some(utils::months_before(today, 12))
+        } else {
+            None
+        };
+        let on_date = utils::get_argument_value("on", matches)?;
+        let from_date = match window_start {
+            Some(date) => Some(date),
+            None => if on_date.is_some() {
+                on_date
+            } else {
+                utils::get_argument_value("from", matches)?
+            }
+            .map(|date| utils::parse_date(date))
+            .transpose()?,
+        }
+        .map(|date| date.and_hms(0, 0, 0));
+        let to_date = if on_date.is_some() {
+            on_date
+        } else {
+            utils::get_argument_value("to", &matches)?
+        }
+        .map(|date| utils::parse_date(date))
+        .transpose()?
+        .map(utils::end_of_day);
+        let added_from_date = utils::get_argument_value("added-from", matches)?
+            .map(|date| utils::parse_date(date))
+            .transpose()?
+            .map(|date| date.and_hms(0, 0, 0));
+        let added_to_date = utils::get_argument_value("added-to", matches)?
+            .map(|date| utils::parse_date(date))
+            .transpose()?
+            .map(utils::end_of_day);
+
+        let (author, book, tags, any_tags, not_tags) = (
+            utils::get_argument_value("author", matches)?,
+            utils::get_argument_value("book", matches)?,
+            utils::get_argument_values("tag", matches)?,
+            utils::get_argument_values("any-tag", matches)?,
+            utils::get_argument_values("not-tag", matches)?,
+        );
+        Ok(Filters {
+            author,
+            book,
+            tags,
+            any_tags,
+            not_tags,
+            min_rating: utils::get_argument_value("min-rating", matches)?
+                .map(utils::parse_rating)
+                .transpose()?
+                .flatten(),
+            from_date,
+            to_date,
+            added_from_date,
+            added_to_date,
+        })
+    }
+}
+
+impl<'a> Quoth<'a> {
+    /// Initialize program
+    pub fn start(matches: ArgMatches<'a>) -> Result<(), Error> {
+        // console already skips styling when stdout isn't a terminal
+        if matches.is_present("no-color") {
+            console::set_colors_enabled(false);
+        }
+        let quoth_dir = &get_quoth_dir()?;
+        let profile =
+            utils::get_argument_value("profile", &matches)?.unwrap_or(config::DEFAULT_PROFILE);
+        let profile_dir = &get_profile_dir(quoth_dir, profile)?;
+        // compact swaps out the database files, so it has to run before the trees are opened
+        if let ("compact", Some(_)) = matches.subcommand() {
+            return Quoth::compact(profile_dir);
+        }
+        let mut trees = Trees::read(profile_dir)?;
+        trees.lowercase_tags = get_lowercase_tags()?;
+        let print_style = PrintStyle {
+            width: get_width(&matches)?,
+            symbol: get_symbol(&matches)?,
+            theme: get_theme()?,
+        };
+        let mut quoth = Quoth {
+            quoth_dir,
+            profile_dir,
+            matches,
+            trees,
+            print_style,
+        };
+        quoth.run()
+    }
+
+    /// Rewrites the quoth database to reclaim unused space
+    fn compact(quoth_dir: &PathDir) -> Result<(), Error> {
+        let (size_before, size_after) = Trees::compact(quoth_dir)?;
+        println!(
+            "Compacted quoth database from {:.1} KB to {:.1} KB",
+            size_before as f64 / 1024.,
+            size_after as f64 / 1024.
+        );
+        Ok(())
+    }
+
+    /// Parses command-line arguments to decide which sub-command to run
+    fn run(&mut self) -> Result<(), Error> {
+        // is_present would also be true for the delete subcommand
+        if self.matches.occurrences_of("delete") > 0 {
+            self.delete_quote()
+        } else if self.matches.is_present("show") {
+            self.show_quote()
+        } else if self.matches.is_present("first") || self.matches.is_present("last") {
+            self.show_end_quote()
+        } else if self.matches.is_present("change") {
+            self.change_quote()
+        } else {
+            match self.matches.subcommand() {
+                ("stats", Some(matches)) => self.stats(matches),
+                ("heatmap", Some(matches)) => self.heatmap(matches),
+                ("browse", Some(matches)) => self.browse(&matches.clone()),
+                ("config", Some(matches)) => self.config(matches),
+                ("import", Some(matches)) => {
+                    let records = self.import(matches)?;
+                    if matches.is_present("dry-run") {
+                        print_import_summary(&records);
+                        return Ok(());
+                    }
+                    // The Quotes-500K dataset is only saved as JSON unless it's loaded too
+                    if matches.is_present("500k") && !matches.is_present("load") {
+                        return Ok(());
+                    }
+                    // Imported quotes get new indices so they can't overwrite existing ones
+                    // (counting only the quotes that were read, so skipped records don't leave gaps)
+                    let quote_index = self.trees.get_quote_index()? + 1;
+                    let mut quotes = Vec::new();
+                    let mut skipped = Vec::new();
+                    let mut new_indices = HashMap::new();
+                    for (i, record) in records.into_iter().enumerate() {
+                        match record {
+                            Ok(quote) => {
+                                new_indices.insert(quote.index, quote_index + quotes.len());
+                                quotes.push(Quote {
+                                    index: quote_index + quotes.len(),
+                                    ..quote
+                                })
+                            }
+                            Err(error) => skipped.push((i + 1, error)),
+                        }
+                    }
+                    // Links between imported quotes follow them to their new indices, other links are dropped
+                    for quote in &mut quotes {
+                        quote.related = quote
+                            .related
+                            .iter()
+                            .filter_map(|index| new_indices.get(index).copied())
+                            .collect();
+                    }
+                    if quotes.len() > config::BATCH_IMPORT_THRESHOLD {
+                        self.trees.add_quotes(&quotes)?;
+                    } else {
+                        for quote in &quotes {
+                            self.trees.add_quote(quote)?;
+                        }
+                    }
+                    println!(
+                        "Imported {} quotes, skipped {} records",
+                        quotes.len(),
+                        skipped.len()
+                    );
+                    print_skipped_records(skipped.iter().map(|(number, error)| (*number, error)));
+                    Ok(())
+                }
+                ("export", Some(matches)) => self.export(matches),
+                ("list", Some(matches)) => self.list(matches),
+                ("wordcount", Some(matches)) => self.wordcount(matches),
+                ("review", Some(matches)) => self.review(&matches.clone()),
+                ("search", Some(matches)) => self.search(matches),
+                ("random", Some(matches)) => self.random(matches),
+                ("today", Some(matches)) => self.today(matches),
+                ("open", Some(matches)) => self.open(matches),
+                ("copy", Some(matches)) => self.copy(matches),
+                ("sync", Some(matches)) => self.sync(matches),
+                ("link", Some(matches)) => self.link(&matches.clone()),
+                ("collection", Some(matches)) => self.collection(&matches.clone()),
+                ("authors", Some(matches)) => self.authors(matches),
+                ("books", Some(_)) => self.books(),
+                ("tags", Some(matches)) => self.tags(matches),
+                ("add", Some(matches)) => self.add(&matches.clone()),
+                ("delete", Some(matches)) => self.delete(&matches.clone()),
+                ("check", Some(matches)) => self.check(&matches.clone()),
+                ("compact-indices", Some(_)) => self.compact_indices(),
+                ("profiles", Some(_)) => self.profiles(),
+                ("reindex", Some(_)) => {
+                    let num_quotes = self.trees.reindex()?;
+                    println!(
+                        "Rebuilt the author, book, tag, word, and hash trees from {} quotes",
+                        num_quotes
+                    );
+                    Ok(())
+                }
+                ("tag", Some(matches)) => self.tag(&matches.clone()),
+                ("author", Some(matches)) => self.author(&matches.clone()),
+                ("book", Some(matches)) => self.book(&matches.clone()),
+                ("dedup", Some(matches)) => {
+                    let dry_run = matches.is_present("dry-run");
+                    self.dedup(dry_run)
+                }
+                _ => self.quoth(self.matches.is_present("allow-dupes")),
+            }
+        }
+    }
+
+    /// Generates shell completions
+    fn completions(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let shell = utils::get_argument_value("completions", matches)?.ok_or(
+            QuothError::OutOfCheeseError {
+                message: "Argument shell not used".into(),
+            },
+        )?;
+        let yaml = load_yaml!("quoth.yml");
+        let mut app = App::from_yaml(yaml);
+        app.gen_completions_to("quoth", shell.parse::<Shell>().unwrap(), &mut io::stdout());
+        Ok(())
+    }
+
+    /// Clears all quoth data or changes the quote directory or generates shell completions
+    fn config(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        if matches.is_present("clear") {
+            self.clear()
+        } else if matches.is_present("dir") {
+            self.relocate(matches)
+        } else if matches.is_present("completions") {
+            self.completions(matches)
+        } else {
+            Err(QuothError::OutOfCheeseError {
+                message: "Unknown/No config argument".into(),
+            }
+            .into())
+        }
+    }
+
+    /// Adds a new quote
+    fn quoth(&mut self, allow_dupes: bool) -> Result<(), Error> {
+        let quote = Quote::from_user(
+            self.trees.get_quote_index()? + 1,
+            None,
+            &self.trees.get_authors()?,
+            &self.trees.get_tags()?,
+        )?;
+        if !allow_dupes {
+            self.confirm_duplicate(&quote)?;
+        }
+        println!(
+            "Added quote #{}",
+            self.trees.add_quote(&quote)?
+        );
+        Ok(())
+    }
+
+    /// Adds a quote from command-line arguments without prompting
+    /// (the quote text is read from stdin if it's piped in, falls back to `quoth` otherwise)
+    fn add(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let quote_text = match utils::get_argument_value("quote", matches)? {
+            Some(quote_text) => quote_text.to_owned(),
+            None if !termion::is_tty(&io::stdin()) => {
+                let mut quote_text = String::new();
+                io::stdin().read_to_string(&mut quote_text)?;
+                if quote_text.trim().is_empty() {
+                    return Err(QuothError::OutOfCheeseError {
+                        message: "No quote text given on stdin".into(),
+                    }
+                    .into());
+                }
+                quote_text.trim().to_owned()
+            }
+            None => return self.quoth(matches.is_present("allow-dupes")),
+        };
+        let (author, book, tags) = (
+            utils::get_argument_value("author", matches)?.ok_or(QuothError::OutOfCheeseError {
+                message: "Argument author not used".into(),
+            })?,
+            utils::get_argument_value("book", matches)?.ok_or(QuothError::OutOfCheeseError {
+                message: "Argument book not used".into(),
+            })?,
+            utils::get_argument_value("tags", matches)?.unwrap_or(""),
+        );
+        let date = match utils::get_argument_value("date", matches)? {
+            Some(date) => utils::parse_date(date)?.and_hms(0, 0, 0),
+            None => Utc::now(),
+        };
+        let quote = Quote::new(
+            self.trees.get_quote_index()? + 1,
+            book,
+            author,
+            tags,
+            date,
+            quote_text,
+        );
+        if !matches.is_present("allow-dupes") {
+            self.confirm_duplicate(&quote)?;
+        }
+        println!("Added quote #{}", self.trees.add_quote(&quote)?);
+        Ok(())
+    }
+
+    /// Asks before adding a quote with the same authors, book, and text as a stored one, doing nothing unless told to
+    /// (or refusing outright if the quote was piped in, as there's no one to ask)
+    fn confirm_duplicate(&self, quote: &Quote) -> Result<(), Error> {
+        let index = match self.trees.find_duplicate(quote)? {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        if !termion::is_tty(&io::stdin()) {
+            return Err(QuothError::DuplicateQuote { index }.into());
+        }
+        let mut sure_add;
+        loop {
+            sure_add = utils::user_input(
+                &format!("Quote #{} already says that, add it anyway Y/N?", index),
+                Some("N"),
+                true,
+            )?
+            .to_ascii_uppercase();
+            if sure_add == "Y" || sure_add == "N" {
+                break;
+            }
+        }
+        if sure_add != "Y" {
+            return Err(QuothError::DoingNothing {
+                message: "One copy is enough.".into(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Changes a quote at a particular index (interactively, or just one field if `--field` is given)
+    fn change_quote(&mut self) -> Result<(), Error> {
+        let index = utils::get_argument_value("change", &self.matches)?
+            .ok_or(QuothError::OutOfCheeseError {
+                message: "Argument change not used".into(),
+            })?
+            .parse::<usize>()?;
+        let old_quote = self.trees.get_quote(index)?;
+        let new_quote = match utils::get_argument_value("field", &self.matches)? {
+            Some(field) => {
+                old_quote.with_field(field, self.matches.value_of("value").unwrap_or(""))?
+            }
+            None => Quote::from_user(
+                index,
+                Some(old_quote),
+                &self.trees.get_authors()?,
+                &self.trees.get_tags()?,
+            )?,
+        };
+        self.trees.change_quote(index, &new_quote)?;
+        println!("Quote #{} changed", index);
+        Ok(())
+    }
+
+    /// Filters a list of quotes by given author/book/tag/date
+    fn filter_quotes(&self, filters: &Filters<'_>) -> Result<Vec<Quote>, Error> {
+        self.iter_filtered_quotes(filters, None)?.collect()
+    }
+
+    /// Lazily filters quotes by given author/book/tag/date, only considering quotes in `candidates` (if given)
+    fn iter_filtered_quotes<'b>(
+        &'b self,
+        filters: &'b Filters<'b>,
+        candidates: Option<HashSet<usize>>,
+    ) -> Result<Box<dyn Iterator<Item = Result<Quote, Error>> + 'b>, Error> {
+        let from_date = utils::date_start(filters.from_date);
+        let to_date = utils::date_end(filters.to_date);
+        let added_from_date = utils::date_start(filters.added_from_date);
+        let added_to_date = utils::date_end(filters.added_to_date);
+        let indices: Option<Vec<_>> = match (filters.author, filters.book) {
+            (Some(author), None) => Some(self.trees.get_author_quotes(author)?),
+            (None, Some(book)) => Some(self.trees.get_book_quotes(book)?),
+            (Some(author), Some(book)) => {
+                let book_indices: HashSet<_> =
+                    self.trees.get_book_quotes(book)?.into_iter().collect();
+                Some(
+                    self.trees
+                        .get_author_quotes(author)?
+                        .into_iter()
+                        .filter(|index| book_indices.contains(index))
+                        .collect(),
+                )
+            }
+            (None, None) => None,
+        };
+        let indices = match (indices, candidates) {
+            (Some(indices), Some(candidates)) => Some(
+                indices
+                    .into_iter()
+                    .filter(|index| candidates.contains(index))
+                    .collect(),
+            ),
+            (None, Some(candidates)) => {
+                let mut candidates: Vec<_> = candidates.into_iter().collect();
+                candidates.sort();
+                Some(candidates)
+            }
+            (indices, None) => indices,
+        };
+        let indices = match (indices, filters.tags.first()) {
+            (Some(indices), _) => Some(indices),
+            (None, Some(tag)) => Some(self.trees.get_tag_quotes(tag)?),
+            (None, None) if !filters.any_tags.is_empty() => {
+                let mut any_tag_indices = Vec::new();
+                for tag in &filters.any_tags {
+                    any_tag_indices.extend(self.trees.get_tag_quotes(tag)?);
+                }
+                any_tag_indices.sort();
+                any_tag_indices.dedup();
+                Some(any_tag_indices)
+            }
+            (None, None) => None,
+        };
+        let quotes: Box<dyn Iterator<Item = Result<Quote, Error>> + 'b> = match indices {
+            Some(indices) => Box::new(
+                indices
+                    .into_iter()
+                    .map(move |index| self.trees.get_quote(index)),
+            ),
+            None => Box::new(self.trees.iter_quotes_in_date_range(from_date, to_date)?),
+        };
+        Ok(Box::new(quotes.filter(move |quote| {
+            match quote {
+                Ok(quote) => {
+                    quote.in_date_range(from_date, to_date)
+                        && quote.added_in_date_range(added_from_date, added_to_date)
+                        && filters.tags.iter().all(|tag| quote.has_tag(tag))
+                        && (filters.any_tags.is_empty()
+                            || filters.any_tags.iter().any(|tag| quote.has_tag(tag)))
+                        && !filters.not_tags.iter().any(|tag| quote.has_tag(tag))
+                        && filters
+                            .min_rating
+                            .map_or(true, |min_rating| quote.rating >= Some(min_rating))
+                }
+                Err(_) => true,
+            }
+        })))
+    }
+
+    /// Shows quotes matching the given indices (missing ones are reported and skipped),
+    /// each followed by its related quotes if `--with-related` is given
+    fn show_quote(&self) -> Result<(), Error> {
+        let mut indices = Vec::new();
+        for indices_string in utils::get_argument_values("show", &self.matches)? {
+            indices.extend(utils::parse_indices(indices_string)?);
+        }
+        for index in indices {
+            let quote = match self.trees.get_quote(index) {
+                Ok(quote) => quote,
+                Err(err) => match err.downcast_ref::<QuothError>() {
+                    Some(QuothError::QuoteNotFound { .. }) => {
+                        eprintln!("{}", err);
+                        continue;
+                    }
+                    _ => return Err(err),
+                },
+            };
+            let related = if self.matches.is_present("with-related") {
+                self.trees.get_quotes(&quote.related)?
+            } else {
+                Vec::new()
+            };
+            for quote in std::iter::once(quote).chain(related) {
+                if self.matches.is_present("json") {
+                    println!("{}", serde_json::to_string(&quote)?);
+                } else {
+                    print_quote(&quote, self.matches.is_present("box"), &self.print_style);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Shows the oldest (`--first`) or newest (`--last`) quote, by index or `--by date`
+    fn show_end_quote(&self) -> Result<(), Error> {
+        let field = utils::get_argument_value("by", &self.matches)?.unwrap_or("index");
+        let quote = self
+            .trees
+            .get_end_quote(field, self.matches.is_present("last"))?;
+        if self.matches.is_present("json") {
+            println!("{}", serde_json::to_string(&quote)?);
+        } else {
+            print_quote(&quote, self.matches.is_present("box"), &self.print_style);
+        }
+        Ok(())
+    }
+
+    /// Renames or merges tags across all quotes
+    fn tag(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        match matches.subcommand() {
+            ("rename", Some(matches)) => {
+                let (old_tag, new_tag) = (
+                    utils::get_argument_value("old", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument old not used".into(),
+                        },
+                    )?,
+                    utils::get_argument_value("new", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument new not used".into(),
+                        },
+                    )?,
+                );
+                let num_changed = self.trees.rename_tag(old_tag, new_tag)?;
+                println!(
+                    "Renamed tag {:?} to {:?} on {} quotes",
+                    utils::clean_tag(old_tag),
+                    self.trees.stored_tag(new_tag),
+                    num_changed
+                );
+                Ok(())
+            }
+            ("merge", Some(matches)) => {
+                let (tag, into_tag) = (
+                    utils::get_argument_value("tag", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument tag not used".into(),
+                        },
+                    )?,
+                    utils::get_argument_value("into", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument into not used".into(),
+                        },
+                    )?,
+                );
+                let num_changed = self.trees.rename_tag(tag, into_tag)?;
+                println!(
+                    "Merged tag {:?} into {:?} on {} quotes",
+                    utils::clean_tag(tag),
+                    self.trees.stored_tag(into_tag),
+                    num_changed
+                );
+                Ok(())
+            }
+            _ => Err(QuothError::OutOfCheeseError {
+                message: "Unknown/No tag subcommand".into(),
+            }
+            .into()),
+        }
+    }
+
+    /// Renames an author across all quotes
+    fn author(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        match matches.subcommand() {
+            ("rename", Some(matches)) => {
+                let (old_author, new_author) = (
+                    utils::get_argument_value("old", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument old not used".into(),
+                        },
+                    )?,
+                    utils::get_argument_value("new", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument new not used".into(),
+                        },
+                    )?,
+                );
+                let num_changed = self.trees.rename_author(old_author, new_author)?;
+                println!(
+                    "Renamed author {:?} to {:?} on {} quotes",
+                    utils::camel_case_phrase(old_author),
+                    utils::camel_case_phrase(new_author),
+                    num_changed
+                );
+                Ok(())
+            }
+            _ => Err(QuothError::OutOfCheeseError {
+                message: "Unknown/No author subcommand".into(),
+            }
+            .into()),
+        }
+    }
+
+    /// Renames a book across all quotes
+    fn book(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        match matches.subcommand() {
+            ("rename", Some(matches)) => {
+                let (old_book, new_book) = (
+                    utils::get_argument_value("old", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument old not used".into(),
+                        },
+                    )?,
+                    utils::get_argument_value("new", matches)?.ok_or(
+                        QuothError::OutOfCheeseError {
+                            message: "Argument new not used".into(),
+                        },
+                    )?,
+                );
+                let num_changed = self.trees.rename_book(old_book, new_book)?;
+                println!(
+                    "Renamed book {:?} to {:?} on {} quotes",
+                    utils::camel_case_phrase(old_book),
+                    utils::camel_case_phrase(new_book),
+                    num_changed
+                );
+                Ok(())
+            }
+            _ => Err(QuothError::OutOfCheeseError {
+                message: "Unknown/No book subcommand".into(),
+            }
+            .into()),
+        }
+    }
+
+    /// Prints each author with their number of quotes, one per line
+    fn authors(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let mut author_counts: Vec<_> = self
+            .trees
+            .get_author_counts()?
+            .into_iter()
+            .map(|(author, (_, num_quotes))| (author, num_quotes))
+            .collect();
+        if matches.is_present("by-count") {
+            author_counts.sort_by(|(a1, q1), (a2, q2)| q2.cmp(q1).then_with(|| a1.cmp(a2)));
+        } else {
+            author_counts.sort();
+        }
+        for (author, num_quotes) in author_counts {
+            println!("{}\t{}", author, num_quotes);
+        }
+        Ok(())
+    }
+
+    /// Prints each book with its author and number of quotes, one per line (sorted by author then title)
+    fn books(&self) -> Result<(), Error> {
+        let mut book_counts: Vec<_> = self
+            .trees
+            .get_book_counts()?
+            .into_iter()
+            .map(|(book, (author, num_quotes))| (author, book, num_quotes))
+            .collect();
+        book_counts.sort();
+        for (author, book, num_quotes) in book_counts {
+            println!("{}\t{}\t{}", book, author, num_quotes);
+        }
+        Ok(())
+    }
+
+    /// Prints each tag with its number of quotes, one per line (sorted by tag unless `--by-count`)
+    fn tags(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let mut tag_counts: Vec<_> = self.trees.get_tag_counts()?.into_iter().collect();
+        if matches.is_present("by-count") {
+            tag_counts.sort_by(|(t1, q1), (t2, q2)| q2.cmp(q1).then_with(|| t1.cmp(t2)));
+        } else {
+            tag_counts.sort();
+        }
+        for (tag, num_quotes) in tag_counts {
+            println!("{}\t{}", tag, num_quotes);
+        }
+        Ok(())
+    }
+
+    /// Opens the source URL of a quote in the default browser
+    fn open(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let index = utils::get_argument_value("index", matches)?
+            .ok_or(QuothError::OutOfCheeseError {
+                message: "Argument index not used".into(),
+            })?
+            .parse::<usize>()
+            .with_context(|| "Given index is not a number")?;
+        match self.trees.get_quote(index)?.source {
+            Some(source) => {
+                let source = utils::parse_source(&source)?.ok_or(QuothError::BadUrl {
+                    url: source.clone(),
+                })?;
+                println!("Opening {}", source);
+                open::that(&source)?;
+            }
+            None => println!("Quote #{} has no source", index),
+        }
+        Ok(())
+    }
+
+    /// Adds quotes to a collection, lists collections, or shows the quotes in a collection
+    fn collection(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        match matches.subcommand() {
+            ("add", Some(matches)) => {
+                let name = utils::get_argument_value("name", matches)?.ok_or(
+                    QuothError::OutOfCheeseError {
+                        message: "Argument name not used".into(),
+                    },
+                )?;
+                let mut indices = Vec::new();
+                for indices_string in utils::get_argument_values("index", matches)? {
+                    indices.extend(utils::parse_indices(indices_string)?);
+                }
+                self.trees.add_to_collection(name, &indices)?;
+                println!("Added {} quotes to collection {:?}", indices.len(), name);
+                Ok(())
+            }
+            ("list", Some(_)) => {
+                for (name, num_quotes) in self.trees.get_collection_counts()? {
+                    println!("{}\t{}", name, num_quotes);
+                }
+                Ok(())
+            }
+            ("show", Some(matches)) => {
+                let name = utils::get_argument_value("name", matches)?.ok_or(
+                    QuothError::OutOfCheeseError {
+                        message: "Argument name not used".into(),
+                    },
+                )?;
+                for quote in self
+                    .trees
+                    .get_quotes(&self.trees.get_collection_quotes(name)?)?
+                {
+                    if self.matches.is_present("json") {
+                        println!("{}", serde_json::to_string(&quote)?);
+                    } else {
+                        quote.pretty_print_with(&self.print_style);
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(QuothError::OutOfCheeseError {
+                message: "Unknown/No collection subcommand".into(),
+            }
+            .into()),
+        }
+    }
+
+    /// Links two quotes as related to each other
+    fn link(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let mut indices = Vec::with_capacity(2);
+        for argument in &["index-1", "index-2"] {
+            indices.push(
+                utils::get_argument_value(argument, matches)?
+                    .ok_or(QuothError::OutOfCheeseError {
+                        message: format!("Argument {} not used", argument),
+                    })?
+                    .parse::<usize>()
+                    .with_context(|| "Given index is not a number")?,
+            );
+        }
+        if self.trees.link_quotes(indices[0], indices[1])? {
+            println!("Linked quotes #{} and #{}", indices[0], indices[1]);
+        } else {
+            println!(
+                "Quotes #{} and #{} are already linked",
+                indices[0], indices[1]
+            );
+        }
+        Ok(())
+    }
+
+    /// Copies a quote to the clipboard as plain text, Markdown, or a TSV row
+    fn copy(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let index = utils::get_argument_value("index", matches)?
+            .ok_or(QuothError::OutOfCheeseError {
+                message: "Argument index not used".into(),
+            })?
+            .parse::<usize>()
+            .with_context(|| "Given index is not a number")?;
+        let quote = self.trees.get_quote(index)?;
+        let text = match utils::get_argument_value("format", matches)?.unwrap_or("plain") {
+            "markdown" => quote.to_markdown(),
+            "tsv" => TSVQuote::from(quote).to_row()?,
+            _ => quote.to_plain(),
+        };
+        let clipboard_error = |error: Box<dyn std::error::Error>| QuothError::ClipboardError {
+            message: error.to_string(),
+        };
+        let mut clipboard: ClipboardContext = ClipboardProvider::new().map_err(clipboard_error)?;
+        clipboard
+            .set_contents(text.clone())
+            .map_err(clipboard_error)?;
+        println!("Copied quote #{} to the clipboard:\n{}", index, text);
+        Ok(())
+    }
+
+    /// Exports all quotes to a file in the quoth directory, commits it to the git repository there,
+    /// and pushes the commit (unless `--no-push` is given or the remote doesn't exist)
+    fn sync(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let repository = Repository::open(self.quoth_dir.as_path()).map_err(|_| {
+            QuothError::NotAGitRepository {
+                path: self.quoth_dir.to_string_lossy().into(),
+            }
+        })?;
+        let format = utils::get_argument_value("format", matches)?.unwrap_or("json");
+        let sync_file =
+            self.profile_dir
+                .as_path()
+                .join(format!("{}.{}", config::SYNC_FILE_NAME, format));
+        // Sorted by index so the file only changes where quotes do
+        let mut quotes = self
+            .trees
+            .list_quotes_in_date_range(utils::date_start(None), utils::date_end(None))?;
+        quotes.sort_by_key(|quote| quote.index);
+        let num_quotes = quotes.len();
+        let writer = Box::new(FileWrite::create(PathFile::create(&sync_file)?)?);
+        write_quotes(
+            quotes.into_iter().map(Ok),
+            writer,
+            format,
+            "%Y-%m-%d",
+            config::FEED_TITLE_DEFAULT,
+            false,
+        )?;
+
+        let mut index = repository.index()?;
+        index.add_path(sync_file.strip_prefix(self.quoth_dir.as_path())?)?;
+        index.write()?;
+        let tree = repository.find_tree(index.write_tree()?)?;
+        // HEAD doesn't point to a commit yet in a new repository
+        let parent = match repository.head() {
+            Ok(head) => Some(head.peel_to_commit()?),
+            Err(_) => None,
+        };
+        if parent.as_ref().map(|parent| parent.tree_id()) == Some(tree.id()) {
+            // Still pushes, in case an earlier sync committed without pushing
+            println!("Nothing to commit, quotes haven't changed since the last commit.");
+        } else {
+            let signature = repository
+                .signature()
+                .or_else(|_| Signature::now("quoth", "quoth@localhost"))?;
+            let message = format!(
+                "Sync {} quotes at {}",
+                num_quotes,
+                Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
+            );
+            let parents: Vec<_> = parent.iter().collect();
+            repository.commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                &message,
+                &tree,
+                &parents,
+            )?;
+            println!("{}", message);
+        }
+
+        if matches.is_present("no-push") {
+            return Ok(());
+        }
+        let remote_name =
+            utils::get_argument_value("remote", matches)?.unwrap_or(config::SYNC_REMOTE_DEFAULT);
+        let mut remote = match repository.find_remote(remote_name) {
+            Ok(remote) => remote,
+            Err(_) => {
+                println!("No remote called {:?}, so I didn't push.", remote_name);
+                return Ok(());
+            }
+        };
+        let head = repository.head()?;
+        let branch = head.name().ok_or(QuothError::OutOfCheeseError {
+            message: "HEAD isn't valid UTF-8".into(),
+        })?;
+        let git_config = repository.config()?;
+        let mut tried_ssh_agent = false;
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(|url, username, allowed| {
+            // libgit2 keeps asking if the credentials it got were rejected
+            if allowed.contains(CredentialType::SSH_KEY) && !tried_ssh_agent {
+                tried_ssh_agent = true;
+                Cred::ssh_key_from_agent(username.unwrap_or("git"))
+            } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                Cred::credential_helper(&git_config, url, username)
+            } else {
+                Err(git2::Error::from_str("no usable credentials"))
+            }
+        });
+        callbacks.push_update_reference(|reference, status| match status {
+            Some(status) => Err(git2::Error::from_str(&format!(
+                "{} was rejected: {}",
+                reference, status
+            ))),
+            None => Ok(()),
+        });
+        let mut push_options = PushOptions::new();
+        push_options.remote_callbacks(callbacks);
+        remote.push(
+            &[format!("{0}:{0}", branch).as_str()],
+            Some(&mut push_options),
+        )?;
+        println!("Pushed {} to {}", branch, remote_name);
+        Ok(())
+    }
+
+    /// Lists quotes (optionally filtered, sorted, and limited)
+    fn list(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let filters = Filters::get_filters(matches)?;
+        let quotes = self.iter_filtered_quotes(&filters, None)?;
+        let group_by = utils::get_argument_value("group-by", matches)?;
+        let sort_field = match (utils::get_argument_value("sort", matches)?, group_by) {
+            (Some(field), _) => Some(field),
+            // quotes in a group are listed by date
+            (None, Some(_)) => Some("date"),
+            (None, None) if matches.is_present("reverse") => Some("index"),
+            (None, None) => None,
+        };
+        let quotes = match sort_field {
+            Some(field) => {
+                let mut quotes = quotes.collect::<Result<Vec<_>, _>>()?;
+                Quote::sort_by_field(&mut quotes, field)?;
+                if matches.is_present("reverse") {
+                    quotes.reverse();
+                }
+                // the sort is stable, so quotes keep their order within a group
+                if let Some(group_field) = group_by {
+                    Quote::sort_by_field(&mut quotes, group_field)?;
+                }
+                Box::new(quotes.into_iter().map(Ok))
+            }
+            None => quotes,
+        };
+        let quotes = match get_limit(matches)? {
+            Some(limit) => Box::new(quotes.take(limit)),
+            None => quotes,
+        };
+        if self.matches.is_present("json") {
+            let quotes = quotes.collect::<Result<Vec<_>, _>>()?;
+            println!("{}", serde_json::to_string(&quotes)?);
+        } else {
+            let mut output = String::new();
+            let mut last_group = None;
+            for quote in quotes {
+                let quote = quote?;
+                if let Some(group_field) = group_by {
+                    let group = if group_field == "author" {
+                        quote.author_names()
+                    } else {
+                        quote.book.clone()
+                    };
+                    if last_group.as_ref() != Some(&group) {
+                        output.push_str(&format!("{}\n", style(&group).bold().underlined()));
+                        last_group = Some(group);
+                    }
+                }
+                if matches.is_present("oneline") {
+                    output.push_str(&format!("{}\n", quote.oneline(self.print_style.width)));
+                } else {
+                    output.push_str(&quote.to_pretty(&self.print_style));
+                }
+            }
+            utils::print_paged(&output, use_pager(matches))?;
+        }
+        Ok(())
+    }
+
+    /// Displays a random quote, or `limit` distinct random quotes (optionally filtered)
+    /// The same seed always picks the same quotes from the same set
+    /// With `--weighted`, higher rated (or with `--weighted recent`, newer) quotes are more likely to be picked
+    fn random(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let filters = Filters::get_filters(matches)?;
+        let quotes = self.filter_quotes(&filters)?;
+        if quotes.is_empty() {
+            return Err(QuothError::NoQuotesFound.into());
+        }
+        let limit = get_limit(matches)?.unwrap_or(1);
+        let mut rng: Box<dyn RngCore> = match utils::get_argument_value("seed", matches)? {
+            Some(seed) => Box::new(StdRng::seed_from_u64(
+                seed.parse::<u64>()
+                    .with_context(|| "Given seed is not a number")?,
+            )),
+            None => Box::new(rand::thread_rng()),
+        };
+        if matches.is_present("weighted") {
+            let mut weights: Vec<f64> = match matches.value_of("weighted").unwrap_or("rating") {
+                "recent" => {
+                    let now = Utc::now();
+                    quotes
+                        .iter()
+                        .map(|quote| {
+                            let age = (now - quote.date).num_days() as f64;
+                            0.5_f64.powf(age / config::RECENT_HALF_LIFE_DAYS)
+                        })
+                        .collect()
+                }
+                // Unrated quotes are as likely as quotes rated 3
+                _ => quotes
+                    .iter()
+                    .map(|quote| f64::from(quote.rating.unwrap_or(3)))
+                    .collect(),
+            };
+            for _ in 0..limit.min(quotes.len()) {
+                let index = WeightedIndex::new(&weights)?.sample(&mut rng);
+                print_quote(&quotes[index], matches.is_present("box"), &self.print_style);
+                weights[index] = 0.;
+            }
+        } else {
+            for quote in quotes.choose_multiple(&mut rng, limit) {
+                print_quote(quote, matches.is_present("box"), &self.print_style);
+            }
+        }
+        Ok(())
+    }
+
+    /// Displays the quote of the day (optionally filtered):
+    /// a random quote chosen with the day number as seed, so it stays the same all day
+    fn today(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let filters = Filters::get_filters(matches)?;
+        let mut quotes = self.filter_quotes(&filters)?;
+        quotes.sort_by_key(|quote| quote.index);
+        let day = Utc::now().date().num_days_from_ce();
+        let mut rng = StdRng::seed_from_u64(day as u64);
+        match quotes.choose(&mut rng) {
+            Some(quote) => {
+                quote.pretty_print_with(&self.print_style);
+                Ok(())
+            }
+            None => Err(QuothError::NoQuotesFound.into()),
+        }
+    }
+
+    /// Searches the list of quotes (optionally filtered) for a pattern and displays (at most `limit`) quotes matching it
+    /// The quote text, author, book, tags, and note are searched (see `Quote::to_string`)
+    /// By default all words in the pattern must appear in order, with `--any` at least one of them must appear
+    fn search(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let pattern =
+            utils::get_argument_value("pattern", matches)?.ok_or(QuothError::OutOfCheeseError {
+                message: "Argument pattern not used".into(),
+            })?;
+        let candidates = if self.trees.has_word_index()? {
+            self.search_candidates(pattern, matches)?
+        } else {
+            None
+        };
+        let pattern = search_pattern(
+            pattern,
+            matches.is_present("literal"),
+            matches.is_present("any"),
+        )?;
+        let filters = Filters::get_filters(matches)?;
+        let quotes = self
+            .iter_filtered_quotes(&filters, candidates)?
+            .filter(|quote| {
+                quote
+                    .as_ref()
+                    .map_or(true, |quote| pattern.is_match(&quote.to_string()))
+            })
+            .take(get_limit(matches)?.unwrap_or(usize::MAX));
+        if self.matches.is_present("json") {
+            Quote::write_json(quotes, io::stdout())?;
+        } else {
+            let mut output = String::new();
+            for quote in quotes {
+                output.push_str(&quote?.to_pretty(&self.print_style));
+            }
+            utils::print_paged(&output, use_pager(matches))?;
+        }
+        Ok(())
+    }
+
+    /// Uses the word index to narrow down which quotes can match a search pattern,
+    /// returns None if the pattern has regex syntax the index can't account for
+    fn search_candidates(
+        &self,
+        pattern: &str,
+        matches: &ArgMatches<'a>,
+    ) -> Result<Option<HashSet<usize>>, Error> {
+        let (terms, any) = if matches.is_present("literal") {
+            (utils::split_words(pattern), false)
+        } else {
+            let terms: Vec<_> = pattern.split_whitespace().collect();
+            if terms
+                .iter()
+                .any(|term| !term.chars().all(char::is_alphanumeric))
+            {
+                return Ok(None);
+            }
+            (
+                terms.into_iter().map(str::to_lowercase).collect(),
+                matches.is_present("any"),
+            )
+        };
+        let mut candidates: Option<HashSet<usize>> = None;
+        for term in terms {
+            let term_indices = self.trees.get_word_quotes(&term)?;
+            candidates = Some(match candidates {
+                None => term_indices,
+                Some(candidates) if any => candidates.union(&term_indices).cloned().collect(),
+                Some(candidates) => candidates.intersection(&term_indices).cloned().collect(),
+            });
+        }
+        Ok(candidates)
+    }
+
+    /// Deletes quotes with the same authors, book, and (normalized) text, keeping the lowest index
+    /// (only lists the duplicate indices if `dry_run` is set)
+    fn dedup(&mut self, dry_run: bool) -> Result<(), Error> {
+        let duplicate_groups = self.trees.get_duplicate_groups()?;
+        let num_duplicates: usize = duplicate_groups.iter().map(|group| group.len() - 1).sum();
+        if dry_run {
+            for group in &duplicate_groups {
+                println!(
+                    "{}",
+                    group
+                        .iter()
+                        .map(|index| format!("#{}", index))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            println!("Found {} duplicate quotes", num_duplicates);
+        } else {
+            for group in &duplicate_groups {
+                for index in &group[1..] {
+                    self.trees.delete_quote(*index)?;
+                }
+            }
+            println!("Removed {} duplicate quotes", num_duplicates);
+        }
+        Ok(())
+    }
+
+    /// Clears all quoth data in the profile in use
+    fn clear(&self) -> Result<(), Error> {
+        let mut sure_delete;
+        loop {
+            sure_delete = utils::user_input("Clear all quoth data Y/N?", Some("N"), true)?
+                .to_ascii_uppercase();
+            if sure_delete == "Y" || sure_delete == "N" {
+                break;
+            }
+        }
+        if sure_delete == "Y" {
+            Trees::clear(self.profile_dir)?;
+            Ok(())
+        } else {
+            Err(QuothError::DoingNothing {
+                message: "I'm a coward.".into(),
+            }
+            .into())
+        }
+    }
+
+    /// Changes quoth directory (moving the data of every profile)
+    fn relocate(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        if self.profile_dir != self.quoth_dir {
+            return Err(QuothError::DoingNothing {
+                message:
+                    "Changing the quoth directory moves all profiles, run it without --profile."
+                        .into(),
+            }
+            .into());
+        }
+        let new_dir =
+            utils::get_argument_value("dir", matches)?.ok_or(QuothError::OutOfCheeseError {
+                message: "Argument dir not used".into(),
+            })?;
+        let new_dir_path = PathDir::create_all(new_dir)?;
+        if &new_dir_path == self.quoth_dir {
+            return Err(QuothError::DoingNothing {
+                message: "Same as old dir.".into(),
+            }
+            .into());
+        }
+        self.trees.relocate(self.quoth_dir, &new_dir_path)?;
+        for profile in list_profiles(self.quoth_dir)?.iter().skip(1) {
+            let old_profile_dir = get_profile_dir(self.quoth_dir, profile)?;
+            Trees::read(&old_profile_dir)?
+                .relocate(&old_profile_dir, &get_profile_dir(&new_dir_path, profile)?)?;
+        }
+        change_quoth_dir(new_dir)?;
+        let mut delete_old_dir;
+        loop {
+            delete_old_dir = utils::user_input("Delete old directory Y/N?", Some("N"), true)?
+                .to_ascii_uppercase();
+            if delete_old_dir == "Y" || delete_old_dir == "N" {
+                break;
+            }
+        }
+        if delete_old_dir == "Y" {
+            self.quoth_dir.clone().remove_all()?;
+            Ok(())
+        } else {
+            Err(QuothError::DoingNothing {
+                message: "I'm a coward.".into(),
+            }
+            .into())
+        }
+    }
+
+    /// Lists profiles, marking the one in use
+    fn profiles(&self) -> Result<(), Error> {
+        for profile in list_profiles(self.quoth_dir)? {
+            if get_profile_dir(self.quoth_dir, &profile)? == *self.profile_dir {
+                println!("* {}", profile);
+            } else {
+                println!("  {}", profile);
+            }
+        }
+        Ok(())
+    }
+
+    /// Renumbers quotes from 1 without gaps (after confirmation)
+    fn compact_indices(&mut self) -> Result<(), Error> {
+        let mut sure_renumber;
+        loop {
+            sure_renumber = utils::user_input("Renumber all quotes Y/N?", Some("N"), true)?
+                .to_ascii_uppercase();
+            if sure_renumber == "Y" || sure_renumber == "N" {
+                break;
+            }
+        }
+        if sure_renumber == "Y" {
+            println!("Renumbered {} quotes", self.trees.renumber()?);
+            Ok(())
+        } else {
+            Err(QuothError::DoingNothing {
+                message: "I'm a coward.".into(),
+            }
+            .into())
+        }
+    }
+
+    /// Reports inconsistencies between the quotes and the author, book, and tag trees (and rebuilds them with `--fix`)
+    fn check(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let problems = self.trees.check()?;
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        if problems.is_empty() {
+            println!("No problems found");
+        } else if matches.is_present("fix") {
+            let num_quotes = self.trees.reindex()?;
+            println!(
+                "Found {} problems, rebuilt the author, book, and tag trees from {} quotes",
+                problems.len(),
+                num_quotes
+            );
+        } else {
+            println!(
+                "Found {} problems (use --fix to rebuild the author, book, and tag trees)",
+                problems.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Deletes quotes at the indices given to `--delete`
+    fn delete_quote(&mut self) -> Result<(), Error> {
+        let mut indices = Vec::new();
+        for indices_string in utils::get_argument_values("delete", &self.matches)? {
+            indices.extend(utils::parse_indices(indices_string)?);
+        }
+        let yes = self.matches.is_present("yes");
+        self.delete_quotes(indices, yes)
+    }
+
+    /// Deletes quotes at the given indices, or all quotes matching filters with `--all`
+    fn delete(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let indices = if matches.is_present("all") {
+            let filters = Filters::get_filters(matches)?;
+            self.filter_quotes(&filters)?
+                .into_iter()
+                .map(|quote| quote.index)
+                .collect()
+        } else {
+            let mut indices = Vec::new();
+            for indices_string in utils::get_argument_values("index", matches)? {
+                indices.extend(utils::parse_indices(indices_string)?);
+            }
+            indices
+        };
+        if indices.is_empty() {
+            return Err(QuothError::NoQuotesFound.into());
+        }
+        self.delete_quotes(indices, matches.is_present("yes"))
+    }
+
+    /// Deletes quotes at the given indices after confirmation (unless `yes`), missing ones are reported and skipped
+    fn delete_quotes(&mut self, indices: Vec<usize>, yes: bool) -> Result<(), Error> {
+        let indices_string = indices
+            .iter()
+            .map(|index| format!("#{}", index))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !yes {
+            let mut sure_delete;
+            loop {
+                sure_delete = utils::user_input(
+                    &format!("Delete quotes {} Y/N?", indices_string),
+                    Some("N"),
+                    true,
+                )?
+                .to_ascii_uppercase();
+                if sure_delete == "Y" || sure_delete == "N" {
+                    break;
+                }
+            }
+            if sure_delete != "Y" {
+                return Err(QuothError::DoingNothing {
+                    message: "I'm a coward.".into(),
+                }
+                .into());
+            }
+        }
+        let mut deleted = Vec::new();
+        for index in indices {
+            match self.trees.delete_quote(index) {
+                Ok(()) => deleted.push(format!("#{}", index)),
+                Err(err) => match err.downcast_ref::<QuothError>() {
+                    Some(QuothError::QuoteNotFound { .. }) => eprintln!("{}", err),
+                    _ => return Err(err),
+                },
+            }
+        }
+        if deleted.is_empty() {
+            println!("Deleted 0 quotes");
+        } else {
+            println!("Deleted {} quotes: {}", deleted.len(), deleted.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Saves (optionally filtered) quotes to a TSV, JSON, or BibTeX file (or stdout if no file given)
+    fn export(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        if let Some(filename) = utils::get_argument_value("filename", matches)? {
+            if !matches.is_present("force")
+                && !matches.is_present("append")
+                && Path::new(filename).exists()
+            {
+                let mut overwrite;
+                loop {
+                    overwrite = utils::user_input(
+                        &format!("{} already exists, overwrite it Y/N?", filename),
+                        Some("N"),
+                        true,
+                    )?
+                    .to_ascii_uppercase();
+                    if overwrite == "Y" || overwrite == "N" {
+                        break;
+                    }
+                }
+                if overwrite == "N" {
+                    return Err(QuothError::DoingNothing {
+                        message: "I'm a coward.".into(),
+                    }
+                    .into());
+                }
+            }
+        }
+        let filters = Filters::get_filters(matches)?;
+        // Whether rows are being added after existing content (so no header/separator is needed before them)
+        let mut appending = false;
+        let writer: Box<dyn io::Write> = match utils::get_argument_value("filename", matches)? {
+            Some(filename) if matches.is_present("append") => {
+                let file = PathFile::create(filename)?;
+                appending = file.metadata()?.len() > 0;
+                Box::new(FileWrite::open_append(file)?)
+            }
+            Some(filename) => Box::new(FileWrite::create(PathFile::create(filename)?)?),
+            None => Box::new(io::stdout()),
+        };
+        let format = if matches.is_present("json") {
+            "json"
+        } else {
+            utils::get_argument_value("format", matches)?.unwrap_or("tsv")
+        };
+        let date_format = utils::get_argument_value("date-format", matches)?.unwrap_or("%Y-%m-%d");
+        utils::check_date_format(date_format)?;
+        let feed_title =
+            utils::get_argument_value("feed-title", matches)?.unwrap_or(config::FEED_TITLE_DEFAULT);
+        let quotes = self.iter_filtered_quotes(&filters, None)?;
+        write_quotes(quotes, writer, format, date_format, feed_title, appending)
+    }
+
+    /// Parses quotes from a JSON/TSV/CSV file to add to quoth, with a result for each record in the file
+    /// (the file itself not being readable is an error)
+    /// Indices aren't assigned here, but when the quotes are added, in the order they're in the file
+    fn import(&self, matches: &ArgMatches<'a>) -> Result<Vec<Result<Quote, Error>>, Error> {
+        if matches.is_present("json") {
+            let json_file = PathFile::new(utils::get_argument_value("json", matches)?.ok_or(
+                QuothError::OutOfCheeseError {
+                    message: "Argument json not used".into(),
+                },
+            )?)?;
+            let quotes = if matches.is_present("parallel") {
+                Quote::read_from_file_parallel(&json_file)?
+            } else {
+                Quote::read_from_file(&json_file)?.collect()
+            };
+            Ok(quotes
+                .into_iter()
+                .map(|quote| quote.map_err(Error::from))
+                .collect())
+        } else if matches.is_present("tsv") || matches.is_present("csv") {
+            let (argument, delimiter) = if matches.is_present("tsv") {
+                ("tsv", b'\t')
+            } else {
+                ("csv", b',')
+            };
+            let delimited_file =
+                PathFile::new(utils::get_argument_value(argument, matches)?.ok_or(
+                    QuothError::OutOfCheeseError {
+                        message: format!("Argument {} not used", argument),
+                    },
+                )?)?;
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .from_path(&delimited_file)?;
+            let quoth_headers: HashMap<&str, i32> = [
+                ("BOOK", 0),
+                ("AUTHOR", 1),
+                ("TAGS", 2),
+                ("DATE", 3),
+                ("QUOTE", 4),
+                ("PAGE", 5),
+                ("NOTE", 6),
+                ("RATING", 7),
+                ("SOURCE", 8),
+            ]
+            .iter()
+            .cloned()
+            .collect();
+            let header_indices: Vec<_> = reader
+                .headers()?
+                .into_iter()
+                .map(|h| quoth_headers.get(h.to_ascii_uppercase().as_str()))
+                .collect();
+            if [0, 1, 4].iter().all(|x| header_indices.contains(&Some(x))) {
+                let records: Vec<_> = reader.records().collect();
+                let parse_record = |record| parse_delimited_record(record, &header_indices);
+                if matches.is_present("parallel") {
+                    Ok(records.into_par_iter().map(parse_record).collect())
+                } else {
+                    Ok(records.into_iter().map(parse_record).collect())
+                }
+            } else {
+                Err(QuothError::FileParseError {
+                    filename: delimited_file
+                        .to_str()
+                        .ok_or(QuothError::OutOfCheeseError {
+                            message: "Bad filename".into(),
+                        })?
+                        .into(),
+                }
+                .into())
+            }
+        } else if matches.is_present("500k") {
+            let dataset_file = utils::get_argument_value("500k", matches)?.ok_or(
+                QuothError::OutOfCheeseError {
+                    message: "Argument 500k not used".into(),
+                },
+            )?;
+            let dataset = utils::read_quotes_database(dataset_file)?;
+            let json_file = Path::new(dataset_file).with_extension("json");
+            PathFile::create(&json_file)?.write_str(&serde_json::to_string(&dataset)?)?;
+            println!(
+                "Saved the quotes of {} authors to {}",
+                dataset.len(),
+                json_file.display()
+            );
+            let date = Utc::now();
+            Ok(dataset
+                .iter()
+                .flat_map(|(author, quotes)| {
+                    quotes.iter().map(move |quote| {
+                        Ok(Quote::new(
+                            0,
+                            &quote.book,
+                            author,
+                            &quote.tags,
+                            date,
+                            quote.quote.clone(),
+                        ))
+                    })
+                })
+                .collect())
+        } else {
+            Err(QuothError::OutOfCheeseError {
+                message: "Can only handle JSON, TSV, or CSV input, or the Quotes-500K dataset"
+                    .into(),
+            }
+            .into())
+        }
+    }
+
+    /// Prints a calendar of quote activity (by default over the last year):
+    /// one column per week and one row per weekday, shaded by the number of quotes recorded that day
+    fn heatmap(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let to_date = utils::get_argument_value("to", matches)?
+            .map(|date| utils::parse_date(date))
+            .transpose()?
+            .unwrap_or_else(|| Utc::now().date());
+        let from_date = utils::get_argument_value("from", matches)?
+            .map(|date| utils::parse_date(date))
+            .transpose()?
+            .unwrap_or_else(|| to_date - Duration::weeks(52) + Duration::days(1));
+        let quote_counts = self
+            .trees
+            .get_quote_counts_per_day(from_date.and_hms(0, 0, 0), utils::end_of_day(to_date))?;
+        let max_count = match quote_counts.values().max() {
+            Some(max_count) => *max_count,
+            None => return Err(QuothError::NoQuotesFound.into()),
+        };
+
+        // Weeks start on Monday, each week takes two columns after the weekday labels
+        let start_date =
+            from_date - Duration::days(i64::from(from_date.weekday().num_days_from_monday()));
+        let num_weeks = ((to_date - start_date).num_days() / 7 + 1) as usize;
+        let weeks_per_line = ((utils::terminal_width() - 4) / 2).max(1);
+        let colored = console::colors_enabled();
+        for first_week in (0..num_weeks).step_by(weeks_per_line) {
+            let weeks = first_week..num_weeks.min(first_week + weeks_per_line);
+            // Month names above the first week of each month (if there's room)
+            let mut month_line = String::from("    ");
+            for week in weeks.clone() {
+                let monday = (start_date + Duration::weeks(week as i64)).max(from_date);
+                let previous_month = (monday - Duration::weeks(1)).max(from_date).month();
+                let column = 4 + (week - first_week) * 2;
+                if (week == first_week || monday.month() != previous_month)
+                    && month_line.len() <= column
+                {
+                    month_line.push_str(&" ".repeat(column - month_line.len()));
+                    month_line.push_str(&monday.format("%b").to_string());
+                }
+            }
+            println!("{}", month_line);
+            for weekday in 0..7 {
+                let mut line = format!("{:<4}", ["Mon", "", "Wed", "", "Fri", "", ""][weekday]);
+                for week in weeks.clone() {
+                    let date = start_date + Duration::days((week * 7 + weekday) as i64);
+                    if date < from_date || date > to_date {
+                        line.push_str("  ");
+                    } else {
+                        let level =
+                            heatmap_level(*quote_counts.get(&date).unwrap_or(&0), max_count);
+                        line.push_str(&heatmap_cell(level, colored));
+                        line.push(' ');
+                    }
+                }
+                println!("{}", line.trim_end());
+            }
+            println!();
+        }
+        println!(
+            "Less {} More    {} quotes on {} days",
+            (0..=4)
+                .map(|level| heatmap_cell(level, colored))
+                .collect::<Vec<_>>()
+                .join(" "),
+            quote_counts.values().sum::<u64>(),
+            quote_counts.len()
+        );
+        Ok(())
+    }
+
+    /// Shows quotes (optionally filtered) that are due for review one by one, never reviewed ones first,
+    /// and records the review time of each quote marked as reviewed
+    fn review(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let filters = Filters::get_filters(matches)?;
+        let now = Utc::now();
+        let mut quotes = Vec::new();
+        for quote in self.iter_filtered_quotes(&filters, None)? {
+            let quote = quote?;
+            if quote.is_due_for_review(now) {
+                quotes.push(quote);
+            }
+        }
+        if quotes.is_empty() {
+            println!("No quotes are due for review");
+            return Ok(());
+        }
+        quotes.sort_by_key(|quote| (quote.last_reviewed, quote.index));
+        if let Some(limit) = get_limit(matches)? {
+            quotes.truncate(limit);
+        }
+        let num_quotes = quotes.len();
+        for (i, quote) in quotes.into_iter().enumerate() {
+            quote.pretty_print_with(&self.print_style);
+            let mut reviewed;
+            loop {
+                reviewed = utils::user_input(
+                    &format!("Mark as reviewed ({}/{}) Y/N/Q?", i + 1, num_quotes),
+                    Some("Y"),
+                    true,
+                )?
+                .to_ascii_uppercase();
+                if reviewed == "Y" || reviewed == "N" || reviewed == "Q" {
+                    break;
+                }
+            }
+            match reviewed.as_str() {
+                "Y" => {
+                    let index = quote.index;
+                    let reviewed_quote = Quote {
+                        last_reviewed: Some(Utc::now()),
+                        reviews: quote.reviews + 1,
+                        ..quote
+                    };
+                    self.trees.change_quote(index, &reviewed_quote)?;
+                }
+                "Q" => break,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the total number of words in quotes (optionally filtered)
+    fn wordcount(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let filters = Filters::get_filters(matches)?;
+        let (mut num_quotes, mut num_words) = (0, 0);
+        for quote in self.iter_filtered_quotes(&filters, None)? {
+            num_quotes += 1;
+            num_words += quote?.word_count();
+        }
+        if num_quotes == 0 {
+            return Err(QuothError::NoQuotesFound.into());
+        }
+        println!(
+            "{} words in {} quotes ({:.1} per quote)",
+            num_words,
+            num_quotes,
+            utils::words_per_quote(num_words, num_quotes)
+        );
+        Ok(())
+    }
+
+    /// Shows quotes (optionally filtered) one at a time in a TUI:
+    /// left/right arrows (or h/l) page through them, g/G jump to the first/last quote,
+    /// / searches, d deletes (after confirmation), the exit key (q unless configured) or Ctrl-c quits
+    fn browse(&mut self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let filters = Filters::get_filters(matches)?;
+        let mut quotes = self.filter_quotes(&filters)?;
+        if quotes.is_empty() {
+            return Err(QuothError::NoQuotesFound.into());
+        }
+        quotes.sort_by_key(|quote| quote.index);
+        let mut browser = Browser::new(quotes);
+        let events_config = get_events_config(matches)?;
+
+        // Terminal initialization
+        let stdout = io::stdout().into_raw_mode()?;
+        let stdout = MouseTerminal::from(stdout);
+        let stdout = AlternateScreen::from(stdout);
+        let backend = TermionBackend::new(stdout);
+        let mut terminal = Terminal::new(backend)?;
+        terminal.hide_cursor()?;
+
+        let events = Events::with_config(events_config);
+
+        let theme = &self.print_style.theme;
+        let (dim, author_style, book_style, tags_style, quote_style, yellow) = (
+            Style::default().modifier(Modifier::DIM),
+            tui_style(&theme.author),
+            tui_style(&theme.book),
+            tui_style(&theme.tags),
+            tui_style(&theme.quote),
+            Style::default().fg(Color::Yellow),
+        );
+        loop {
+            let quote = &browser.quotes[browser.current];
+            let mut text = vec![
+                Text::styled(format!("{}\n\n", self.print_style.symbol), dim),
+                Text::styled(format!("{}\n\n", quote.quote), quote_style),
+            ];
+            if let Some(note) = &quote.note {
+                text.push(Text::styled(format!("{}\n\n", note), dim));
+            }
+            text.push(Text::styled(format!("--#{}--", quote.index), dim));
+            let mut details = vec![Text::styled(
+                format!("{}\n", quote.author_names()),
+                author_style,
+            )];
+            details.push(Text::styled(
+                match quote.page {
+                    Some(page) => format!("{}, p. {}\n", quote.book, page),
+                    None => format!("{}\n", quote.book),
+                },
+                book_style,
+            ));
+            if let Some(source) = &quote.source {
+                details.push(Text::styled(format!("{}\n", source), dim));
+            }
+            if let Some(rating) = quote.rating {
+                details.push(Text::styled(
+                    format!(
+                        "{}{}\n",
+                        "★".repeat(rating as usize),
+                        "☆".repeat(5usize.saturating_sub(rating as usize))
+                    ),
+                    yellow,
+                ));
+            }
+            details.push(Text::styled(quote.tags.join(", "), tags_style));
+            let status = match &browser.mode {
+                BrowseMode::Normal => format!(
+                    "{}/{}  Page: arrow keys or h/l  Search: /  Delete: d  Quit: {}  {}",
+                    browser.current + 1,
+                    browser.quotes.len(),
+                    events::key_name(events_config.exit_key),
+                    browser.message
+                ),
+                BrowseMode::Search(pattern) => format!("/{}", pattern),
+                BrowseMode::ConfirmDelete => format!("Delete quote #{} Y/N?", quote.index),
+            };
+
+            terminal.draw(|mut f| {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(2)
+                    .constraints(
+                        [
+                            Constraint::Min(3),
+                            Constraint::Length(details.len() as u16),
+                            Constraint::Length(1),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(f.size());
+                Paragraph::new(text.iter())
+                    .alignment(Alignment::Center)
+                    .wrap(true)
+                    .render(&mut f, chunks[0]);
+                Paragraph::new(details.iter())
+                    .alignment(Alignment::Right)
+                    .render(&mut f, chunks[1]);
+                Paragraph::new([Text::styled(status, dim)].iter()).render(&mut f, chunks[2]);
+            })?;
+
+            if let Event::Input(key) = events.next()? {
+                match browser.update(key, events_config.exit_key) {
+                    BrowseAction::Redraw => (),
+                    BrowseAction::Quit => break,
+                    BrowseAction::Delete(index) => {
+                        self.trees.delete_quote(index)?;
+                        if !browser.remove_current() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Uses termion and tui to display a dashboard with 4 components
+    /// 1. Number of quotes written per month as a bar chart
+    /// 2. Number of books read per month as a bar chart
+    /// 3. A table of the number of books and quotes corresponding to each author
+    /// 4. Total numbers of quotes, books, authors, tags, and words recorded in quoth
+    ///
+    /// Keys:
+    /// - left/right arrows or h/l scroll the bar charts, Home/End jump to the first/last month
+    /// - up/down arrows or j/k scroll the author table, PageUp/PageDown by a page,
+    ///   g/G jump to its top/bottom
+    /// - s sorts the author table by the next of `config::AUTHOR_SORT_FIELDS`
+    /// - the exit key (`--exit-key` or the `exit-key` setting, q by default) quits
+    ///
+    /// With `--plain` or `--json`, prints the same numbers instead
+    fn stats(&self, matches: &ArgMatches<'a>) -> Result<(), Error> {
+        let from_date = utils::get_argument_value("from", matches)?
+            .map(|date| utils::parse_date(date))
+            .transpose()?
+            .map(|date| date.and_hms(0, 0, 0))
+            .unwrap_or_else(|| MIN_DATE.and_hms(0, 0, 0));
+        let to_date = utils::get_argument_value("to", &matches)?
+            .map(|date| utils::parse_date(date))
+            .transpose()?
+            .map(utils::end_of_day)
+            .unwrap_or_else(|| utils::end_of_day(MAX_DATE));
+
+        let mut author_sort = utils::get_argument_value("sort-authors", matches)?.unwrap_or("name");
+        if matches.is_present("plain") || matches.is_present("json") {
+            let mut stats = self.trees.get_stats(from_date, to_date)?;
+            AuthorCounts::sort_by_field(&mut stats.authors, author_sort)?;
+            if matches.is_present("json") {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print_plain_stats(&stats);
+            }
+            return Ok(());
+        }
+
+        let (quote_counts, book_counts) =
+            self.trees.get_quote_and_book_counts_per_month(from_date, to_date)?;
+        let (min_date, max_date) = match (quote_counts.keys().min(), quote_counts.keys().max()) {
+            (Some(min_date), Some(max_date)) => (*min_date, *max_date),
+            _ => return Err(QuothError::NoQuotesFound.into()),
+        };
+        let (max_books, max_quotes) = (
+            *book_counts.values().max().unwrap_or(&0),
+            *quote_counts.values().max().unwrap_or(&0),
+        );
+        let months = utils::get_months(min_date, max_date);
+        let mut author_counts = self.trees.get_author_table()?;
+        AuthorCounts::sort_by_field(&mut author_counts, author_sort)?;
+        let mut author_table = make_author_table(&author_counts);
+        let (num_quotes, num_books, num_authors, num_tags, num_words) = (
+            self.trees.quote_tree()?.len(),
+            self.trees.book_quote_tree()?.len(),
+            self.trees.author_quote_tree()?.len(),
+            self.trees.get_tag_counts()?.len(),
+            self.trees.get_word_count()?,
+        );
+
+        let events_config = get_events_config(matches)?;
+
+        //         Terminal initialization
+        let stdout = io::stdout().into_raw_mode()?;
+        let stdout = MouseTerminal::from(stdout);
+        let stdout = AlternateScreen::from(stdout);
+        let backend = TermionBackend::new(stdout);
+        let mut terminal = Terminal::new(backend)?;
+        terminal.hide_cursor()?;
+
+        //         Setup event handlers
+        let events = Events::with_config(events_config);
+
+        //         Get counts
+        let bar_width = 5;
+        let num_rows = (terminal.size()?.height / 5 - 4) as usize;
+        let num_bars = utils::terminal_width() / bar_width;
+
+
+        fn format_date(date: Date<Utc>) -> String {
+            let year = date.year().to_string().chars().skip(2).collect::<String>();
+            format!("{}-{}", date.month(), year)
+        }
+
+        let book_counts: Vec<(String, u64)> = months
+            .iter()
+            .map(|m| (format_date(*m), *(book_counts.get(m).unwrap_or(&0))))
+            .collect();
+        let quote_counts: Vec<(String, u64)> = months
+            .iter()
+            .map(|m| (format_date(*m), *(quote_counts.get(m).unwrap_or(&0))))
+            .collect();
+        let num_bars = num_bars.min(quote_counts.len());
+        let num_rows = num_rows.min(author_table.len());
+        let mut scrollers = Scrollers {
+            start_index_bar: 0,
+            end_index_bar: num_bars,
+            max_index_bar: quote_counts.len(),
+            num_bars,
+            start_index_table: 0,
+            end_index_table: num_rows,
+            max_index_table: author_table.len(),
+            num_rows,
+        };
+        let theme = &self.print_style.theme;
+        loop {
+            terminal.draw(|mut f| {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .margin(2)
+                    .constraints(
+                        [
+                            Constraint::Percentage(40),
+                            Constraint::Percentage(40),
+                            Constraint::Percentage(20),
+                        ]
+                        .as_ref(),
+                    )
+                    .split(f.size());
+
+                // Quote Stats
+                BarChart::default()
+                    .block(Block::default().title("Quotes").borders(Borders::ALL))
+                    .data(
+                        &quote_counts[scrollers.start_index_bar..scrollers.end_index_bar]
+                            .iter()
+                            .map(|(m, x)| (m.as_str(), *x))
+                            .collect::<Vec<_>>(),
+                    )
+                    .bar_width(bar_width as u16)
+                    .max(max_quotes)
+                    .style(tui_style(&theme.chart))
+                    .value_style(Style::default().bg(Color::Black))
+                    .render(&mut f, chunks[0]);
+
+                // Book Stats
+                BarChart::default()
+                    .block(Block::default().title("Books").borders(Borders::ALL))
+                    .data(
+                        &book_counts[scrollers.start_index_bar..scrollers.end_index_bar]
+                            .iter()
+                            .map(|(m, x)| (m.as_str(), *x))
+                            .collect::<Vec<_>>(),
+                    )
+                    .bar_width(bar_width as u16)
+                    .max(max_books)
+                    .style(tui_style(&theme.book))
+                    .value_style(Style::default().bg(Color::Black))
+                    .render(&mut f, chunks[1]);
+
+                {
+                    let chunks = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints(
+                            [Constraint::Percentage(70), Constraint::Percentage(30)].as_ref(),
+                        )
+                        .split(chunks[2]);
+
+                    // Author Stats
+                    let row_style = Style::default().fg(Color::White);
+                    let header_style = tui_style(&theme.author).modifier(Modifier::BOLD);
+                    Table::new(
+                        vec!["Author", "Books", "Quotes"].into_iter(),
+                        author_table[scrollers.start_index_table..scrollers.end_index_table]
+                            .iter()
+                            .map(|row| Row::StyledData(row.iter(), row_style)),
+                    )
+                    .header_style(header_style)
+                    .block(
+                        Block::default()
+                            .title(&format!("Authors (by {})", author_sort))
+                            .borders(Borders::ALL),
+                    )
+                    .widths(&[25, 5, 5])
+                    .render(&mut f, chunks[0]);
+
+                    // Total Stats
+                    Paragraph::new(
+                        vec![
+                            Text::styled(
+                                format!("{}\n", self.print_style.symbol),
+                                Style::default().modifier(Modifier::DIM),
+                            ),
+                            Text::raw(&format!("# Quotes {}\n", num_quotes)),
+                            Text::styled(
+                                &format!("# Books {}\n", num_books),
+                                tui_style(&theme.book),
+                            ),
+                            Text::styled(
+                                &format!("# Authors {}\n", num_authors),
+                                tui_style(&theme.author),
+                            ),
+                            Text::styled(
+                                &format!("# Tags {}\n", num_tags),
+                                tui_style(&theme.tags),
+                            ),
+                            Text::raw(format!(
+                                "# Words {} ({:.1} per quote)\n",
+                                num_words,
+                                utils::words_per_quote(num_words, num_quotes)
+                            )),
+                            Text::raw(format!(
+                                "\nScroll: arrow keys or hjkl\nPgUp/PgDn, Home/End, g/G\nSort authors: s\nQuit: {}\n",
+                                events::key_name(events_config.exit_key)
+                            )),
+                        ]
+                        .iter(),
+                    )
+                    .block(Block::default().title("Total").borders(Borders::ALL))
+                    .alignment(Alignment::Center)
+                    .render(&mut f, chunks[1]);
+                }
+            })?;
+
+            if let Event::Input(input) = events.next()? {
+                if input == events_config.exit_key {
+                    break;
+                } else if input == Key::Char('s') {
+                    let position = config::AUTHOR_SORT_FIELDS
+                        .iter()
+                        .position(|field| *field == author_sort)
+                        .unwrap_or(0);
+                    author_sort = config::AUTHOR_SORT_FIELDS
+                        [(position + 1) % config::AUTHOR_SORT_FIELDS.len()];
+                    AuthorCounts::sort_by_field(&mut author_counts, author_sort)?;
+                    author_table = make_author_table(&author_counts);
+                    scrollers.reset_table(author_table.len());
+                } else {
+                    scrollers.update(input);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rows of the author table in the stats TUI: author, number of books, number of quotes
+fn make_author_table(author_counts: &[AuthorCounts]) -> Vec<Vec<String>> {
+    author_counts
+        .iter()
+        .map(|row| {
+            vec![
+                row.author.clone(),
+                row.books.to_string(),
+                row.quotes.to_string(),
+            ]
+        })
+        .collect()
+}
+
+/// Shade (0 to 4) of a day with `count` quotes in the heatmap, relative to the busiest day
+fn heatmap_level(count: u64, max_count: u64) -> usize {
+    if count == 0 {
+        0
+    } else {
+        1 + ((count - 1) * 4 / max_count.max(1)) as usize
+    }
+}
+
+/// A heatmap cell: block characters get denser (and greener, when colors are enabled) with the shade
+fn heatmap_cell(level: usize, colored: bool) -> String {
+    let cell = ["·", "░", "▒", "▓", "█"][level];
+    if !colored {
+        return cell.into();
+    }
+    let color = match level {
+        0 => termion::color::AnsiValue::grayscale(8),
+        _ => termion::color::AnsiValue::rgb(0, 1 + level as u8, 0),
+    };
+    format!(
+        "{}{}{}",
+        termion::color::Fg(color),
+        cell,
+        termion::color::Fg(termion::color::Reset)
+    )
+}
+
+/// Prints stats as plain text: a table of counts per month, a table of counts per author, and totals
+fn print_plain_stats(stats: &Stats) {
+    println!("{}", style("Month    Quotes  Books").bold());
+    for month in &stats.months {
+        println!("{:<7} {:>7} {:>6}", month.month, month.quotes, month.books);
+    }
+    let author_width = stats
+        .authors
+        .iter()
+        .map(|row| UnicodeWidthStr::width(row.author.as_str()))
+        .max()
+        .unwrap_or(0)
+        .max("Author".len());
+    println!(
+        "\n{}",
+        style(format!(
+            "{}  Books  Quotes",
+            utils::pad_lines("Author", author_width, console::Alignment::Left)
+        ))
+        .bold()
+    );
+    for row in &stats.authors {
+        println!(
+            "{}  {:>5}  {:>6}",
+            utils::pad_lines(&row.author, author_width, console::Alignment::Left),
+            row.books,
+            row.quotes
+        );
+    }
+    let totals = &stats.totals;
+    println!(
+        "\n# Quotes {}\n# Books {}\n# Authors {}\n# Tags {}\n# Words {} ({:.1} per quote)",
+        totals.quotes,
+        totals.books,
+        totals.authors,
+        totals.tags,
+        totals.words,
+        totals.words_per_quote
+    );
+}
+
+/// What keys do in `browse`
+#[derive(Debug, PartialEq)]
+enum BrowseMode {
+    Normal,
+    /// Typing a search pattern
+    Search(String),
+    /// Waiting for Y/N before deleting the current quote
+    ConfirmDelete,
+}
+
+/// What `browse` does after a key press
+#[derive(Debug, PartialEq)]
+enum BrowseAction {
+    Redraw,
+    Quit,
+    /// Deletes the quote with this index (the current quote) from the database
+    Delete(usize),
+}
+
+/// The quotes shown by `browse`, which one is shown, and what keys do
+struct Browser {
+    quotes: Vec<Quote>,
+    current: usize,
+    mode: BrowseMode,
+    /// Shown in the status line until the next key press
+    message: String,
+}
+
+impl Browser {
+    fn new(quotes: Vec<Quote>) -> Self {
+        Browser {
+            quotes,
+            current: 0,
+            mode: BrowseMode::Normal,
+            message: String::new(),
+        }
+    }
+
+    fn update(&mut self, key: Key, exit_key: Key) -> BrowseAction {
+        self.message.clear();
+        let mut action = BrowseAction::Redraw;
+        self.mode = match (mem::replace(&mut self.mode, BrowseMode::Normal), key) {
+            (_, Key::Ctrl('c')) => return BrowseAction::Quit,
+            (BrowseMode::Normal, key) if key == exit_key => return BrowseAction::Quit,
+            (BrowseMode::Normal, Key::Right) | (BrowseMode::Normal, Key::Char('l')) => {
+                self.current = (self.current + 1).min(self.quotes.len() - 1);
+                BrowseMode::Normal
+            }
+            (BrowseMode::Normal, Key::Left) | (BrowseMode::Normal, Key::Char('h')) => {
+                self.current = self.current.saturating_sub(1);
+                BrowseMode::Normal
+            }
+            (BrowseMode::Normal, Key::Char('g')) => {
+                self.current = 0;
+                BrowseMode::Normal
+            }
+            (BrowseMode::Normal, Key::Char('G')) => {
+                self.current = self.quotes.len() - 1;
+                BrowseMode::Normal
+            }
+            (BrowseMode::Normal, Key::Char('/')) => BrowseMode::Search(String::new()),
+            (BrowseMode::Normal, Key::Char('d')) => BrowseMode::ConfirmDelete,
+            (BrowseMode::Search(pattern), Key::Char('\n')) => {
+                // Finds the next match, wrapping around to the first quote
+                let pattern = pattern.to_lowercase();
+                let quotes = &self.quotes;
+                match (1..=quotes.len())
+                    .map(|offset| (self.current + offset) % quotes.len())
+                    .find(|&i| quotes[i].to_string().to_lowercase().contains(&pattern))
+                {
+                    Some(i) => self.current = i,
+                    None => self.message = format!("No quotes match {:?}", pattern),
+                }
+                BrowseMode::Normal
+            }
+            (BrowseMode::Search(mut pattern), Key::Char(c)) => {
+                pattern.push(c);
+                BrowseMode::Search(pattern)
+            }
+            (BrowseMode::Search(mut pattern), Key::Backspace) => {
+                pattern.pop();
+                BrowseMode::Search(pattern)
+            }
+            (BrowseMode::Search(pattern), key) if key != Key::Esc => BrowseMode::Search(pattern),
+            (BrowseMode::ConfirmDelete, Key::Char('y'))
+            | (BrowseMode::ConfirmDelete, Key::Char('Y')) => {
+                action = BrowseAction::Delete(self.quotes[self.current].index);
+                BrowseMode::Normal
+            }
+            _ => BrowseMode::Normal,
+        };
+        action
+    }
+
+    /// Drops the current quote after it's deleted, returns false if there are no quotes left
+    fn remove_current(&mut self) -> bool {
+        let index = self.quotes.remove(self.current).index;
+        if self.quotes.is_empty() {
+            return false;
+        }
+        self.current = self.current.min(self.quotes.len() - 1);
+        self.message = format!("Deleted quote #{}", index);
+        true
+    }
+}
+
+struct Scrollers {
+    num_bars: usize,
+    start_index_bar: usize,
+    end_index_bar: usize,
+    max_index_bar: usize,
+    start_index_table: usize,
+    end_index_table: usize,
+    max_index_table: usize,
+    num_rows: usize,
+}
+
+impl Scrollers {
+    /// Scrolls the author table back to the top (e.g. after re-sorting it)
+    fn reset_table(&mut self, max_index_table: usize) {
+        self.max_index_table = max_index_table;
+        self.start_index_table = 0;
+        self.end_index_table = self.num_rows.min(max_index_table);
+    }
+
+    fn update(&mut self, key: Key) {
+        match key {
+            Key::Right | Key::Char('l') => self.scroll_bars(1),
+            Key::Left | Key::Char('h') => self.scroll_bars(-1),
+            Key::End => self.scroll_bars(self.max_index_bar as isize),
+            Key::Home => self.scroll_bars(-(self.max_index_bar as isize)),
+            Key::Down | Key::Char('j') => self.scroll_table(1),
+            Key::Up | Key::Char('k') => self.scroll_table(-1),
+            Key::PageDown => self.scroll_table(self.num_rows as isize),
+            Key::PageUp => self.scroll_table(-(self.num_rows as isize)),
+            Key::Char('G') => self.scroll_table(self.max_index_table as isize),
+            Key::Char('g') => self.scroll_table(-(self.max_index_table as isize)),
+            _ => (),
+        }
+    }
+
+    /// Moves the bar charts by `offset` months, stopping at the first and last month
+    fn scroll_bars(&mut self, offset: isize) {
+        self.start_index_bar = clamp_scroll(
+            self.start_index_bar,
+            offset,
+            self.max_index_bar - self.num_bars,
+        );
+        self.end_index_bar = self.start_index_bar + self.num_bars;
+    }
+
+    /// Moves the author table by `offset` rows, stopping at the first and last author
+    fn scroll_table(&mut self, offset: isize) {
+        self.start_index_table = clamp_scroll(
+            self.start_index_table,
+            offset,
+            self.max_index_table - self.num_rows,
+        );
+        self.end_index_table = self.start_index_table + self.num_rows;
+    }
+}
+
+/// Adds `offset` to `start`, keeping the result between 0 and `max_start`
+fn clamp_scroll(start: usize, offset: isize, max_start: usize) -> usize {
+    (start as isize + offset).max(0).min(max_start as isize) as usize
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unbalanced_parentheses_are_a_bad_pattern() {
+        let error = search_pattern("(left hand", false, false).unwrap_err();
+        match error.downcast_ref::<QuothError>() {
+            Some(QuothError::BadPattern { pattern }) => assert_eq!(pattern, "(left hand"),
+            _ => panic!("unexpected error {:?}", error),
+        }
+        assert!(search_pattern("(left hand", false, true).is_err());
+        assert!(search_pattern("(left hand", true, false)
+            .unwrap()
+            .is_match("Light is the (Left Hand of darkness"));
+    }
+
+    fn new_browser() -> Browser {
+        Browser::new(vec![
+            Quote::new(
+                1,
+                "The Dispossessed",
+                "Ursula K. Le Guin",
+                "",
+                Utc::now(),
+                "True journey is return.".into(),
+            ),
+            Quote::new(
+                2,
+                "The Lathe Of Heaven",
+                "Ursula K. Le Guin",
+                "",
+                Utc::now(),
+                "Self goes beyond self.".into(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn the_exit_key_can_be_typed_in_a_search() {
+        let exit_key = Key::Char('q');
+        let mut browser = new_browser();
+        for c in "/quote".chars() {
+            assert_eq!(browser.update(Key::Char(c), exit_key), BrowseAction::Redraw);
+        }
+        assert_eq!(browser.mode, BrowseMode::Search("quote".into()));
+        assert_eq!(browser.update(Key::Esc, exit_key), BrowseAction::Redraw);
+        assert_eq!(browser.update(exit_key, exit_key), BrowseAction::Quit);
+    }
+
+    #[test]
+    fn the_exit_key_cancels_a_delete() {
+        let exit_key = Key::Char('q');
+        let mut browser = new_browser();
+        browser.update(Key::Char('l'), exit_key);
+        browser.update(Key::Char('d'), exit_key);
+        assert_eq!(browser.update(exit_key, exit_key), BrowseAction::Redraw);
+        assert_eq!(browser.mode, BrowseMode::Normal);
+        browser.update(Key::Char('d'), exit_key);
+        assert_eq!(
+            browser.update(Key::Char('y'), exit_key),
+            BrowseAction::Delete(2)
+        );
+        assert!(browser.remove_current());
+        assert_eq!(browser.quotes[browser.current].index, 1);
+        assert_eq!(browser.message, "Deleted quote #2");
+    }
+}
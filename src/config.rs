@@ -1,7 +1,71 @@
-/// Location of file containing location of quoth directory (relative to $HOME)
+/// Location of file containing location of quoth directory (relative to $HOME, or to $XDG_CONFIG_HOME/quoth).
+/// The first line is the quoth directory, the following lines can hold settings as `name = value`
 pub const CONFIG_PATH: &str = "quoth.txt";
 /// Default quoth directory (relative to $HOME)
 pub const QUOTH_DIR_DEFAULT: &str = ".quoth";
+/// Name of quoth's directories in $XDG_CONFIG_HOME and $XDG_DATA_HOME (used instead of ~/quoth.txt and ~/.quoth when set)
+pub const XDG_DIR_NAME: &str = "quoth";
 /// Location of sled db (relative to quoth directory)
 pub const DB_PATH: &str = "quoth_db";
+/// Directory containing one directory per named profile (relative to quoth directory)
+pub const PROFILES_PATH: &str = "profiles";
+/// Profile used when `--profile` isn't given, its data lives directly in the quoth directory
+pub const DEFAULT_PROFILE: &str = "default";
 
+/// Location of the fresh sled db written by `quoth compact`, or when upgrading an old db (relative to quoth directory)
+pub const COMPACT_DB_PATH: &str = "quoth_db_compact";
+/// Location the old sled db is moved to while the fresh one is swapped in (relative to quoth directory)
+pub const OLD_DB_PATH: &str = "quoth_db_old";
+
+/// Imports with more quotes than this are written with `Trees::add_quotes` instead of one `add_quote` per quote
+pub const BATCH_IMPORT_THRESHOLD: usize = 100;
+
+/// Narrowest terminal width quotes are laid out for (`termwidth` can be tiny or 0 when not printing to a terminal)
+pub const MIN_TERMINAL_WIDTH: usize = 20;
+
+/// How similar (0 to 1, see `utils::closest_match`) a new author has to be to an existing one
+/// for `quoth` to ask whether the existing author was meant
+pub const AUTHOR_MATCH_THRESHOLD: f64 = 0.8;
+
+/// Fields the author table of `quoth stats` can be sorted by (`s` cycles through them in the TUI)
+pub const AUTHOR_SORT_FIELDS: [&str; 3] = ["name", "quotes", "books"];
+
+/// Config file setting for the key that quits the TUIs (e.g. `exit-key = esc`)
+pub const EXIT_KEY_SETTING: &str = "exit-key";
+/// Config file setting for how often the TUIs tick, in milliseconds (e.g. `tick-rate = 1000`)
+pub const TICK_RATE_SETTING: &str = "tick-rate";
+
+/// Config file setting for the width quotes are laid out for instead of the terminal's width (e.g. `width = 60`)
+pub const WIDTH_SETTING: &str = "width";
+
+/// Config file setting for the symbol shown above quotes instead of the raven, for fonts without it
+/// (e.g. `symbol = *`, or `symbol =` for none)
+pub const SYMBOL_SETTING: &str = "symbol";
+
+/// Config file setting to keep the case of tags instead of lowercasing them (`lowercase-tags = false`),
+/// tags are found whatever their case either way
+pub const LOWERCASE_TAGS_SETTING: &str = "lowercase-tags";
+
+/// Config file setting naming the built-in color theme: "default" or "mono" (no colors)
+pub const THEME_SETTING: &str = "theme";
+
+/// Prefix of config file settings replacing the theme's style for a part of a quote (one of `Theme::ROLES`),
+/// with space-separated color and attribute names (e.g. `color.book = magenta bold`)
+pub const COLOR_SETTING_PREFIX: &str = "color.";
+
+/// Days to wait before reviewing a quote again after its first, second, third, and later reviews (see `quoth review`)
+pub const REVIEW_INTERVALS: [i64; 4] = [1, 3, 7, 30];
+
+/// Days after which a quote is half as likely to be picked by `quoth random --weighted recent`
+pub const RECENT_HALF_LIFE_DAYS: f64 = 365.;
+
+/// Pager used by `quoth list` and `quoth search` when $PAGER isn't set
+pub const PAGER_DEFAULT: &str = "less";
+
+/// Channel title of RSS feeds written by `quoth export --format rss` unless `--feed-title` is given
+pub const FEED_TITLE_DEFAULT: &str = "Quotes";
+
+/// Name (without extension) of the file `quoth sync` exports quotes to before committing them
+pub const SYNC_FILE_NAME: &str = "quotes";
+/// Remote `quoth sync` pushes to unless `--remote` is given
+pub const SYNC_REMOTE_DEFAULT: &str = "origin";
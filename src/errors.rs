@@ -16,6 +16,12 @@ pub enum QuothError {
     /// Thrown when trying to access an unrecorded tag
     #[error("You haven't tagged anything as {tag:?} yet.")]
     TagNotFound { tag: String },
+    /// Thrown when trying to access a nonexistent collection
+    #[error("You haven't collected anything as {name:?} yet.")]
+    CollectionNotFound { name: String },
+    /// Thrown when no quotes match the given filters
+    #[error("I couldn't find any quotes.")]
+    NoQuotesFound,
     /// Thrown when no text is returned from an external editor
     #[error("Your editor of choice didn't work.")]
     EditorError,
@@ -25,13 +31,63 @@ pub enum QuothError {
     /// Catch-all for stuff that should never happen
     #[error("{message:?}\nRedo from start.")]
     OutOfCheeseError { message: String },
+    /// Thrown when adding a quote that's already stored without a terminal to ask whether to add it anyway
+    #[error("Quote {index:?} already says that. Use --allow-dupes to add it anyway.")]
+    DuplicateQuote { index: usize },
     /// Thrown when explicit Y not received from user for destructive things
     #[error("{message:?}\nDoing nothing.")]
     DoingNothing { message: String },
+    /// Thrown when trying to change a field a quote doesn't have
+    #[error("Quotes don't have a {field:?}. Try book, author, tags, date, quote, page, note, rating, or source.")]
+    UnknownField { field: String },
+    /// Thrown when a rating isn't a number from 1 to 5
+    #[error("Ratings go from 1 to 5, {rating:?} doesn't.")]
+    BadRating { rating: String },
+    /// Thrown when a quote source isn't an http(s) URL
+    #[error("{url:?} doesn't look like a URL.")]
+    BadUrl { url: String },
+    /// Thrown when a date format string isn't understood by chrono
+    #[error("I don't understand the date format {format:?}.")]
+    DateFormatError { format: String },
+    /// Thrown when a date isn't understood by `utils::parse_date`
+    #[error("I don't understand the date {date:?}. Try something like 2020-01-31, yesterday, last monday, or 3 days ago.")]
+    DateParseError { date: String },
+    /// Thrown when a search pattern isn't a valid regex
+    #[error(
+        "{pattern:?} isn't a pattern I can search for. Use --literal to search for it as it is."
+    )]
+    BadPattern { pattern: String },
+    /// Thrown when an index range's start comes after its end
+    #[error("{range:?} doesn't look like a range of indices. Try something like 3..8.")]
+    BadIndexRange { range: String },
+    /// Thrown when not all quotes could be copied to a new quoth directory
+    #[error("Only {num_relocated} of {num_quotes} quotes made it to the new quoth directory, so I left the old one alone.")]
+    RelocationError {
+        num_quotes: usize,
+        num_relocated: usize,
+    },
+    /// Thrown when a profile name can't be used as a directory name
+    #[error("{name:?} can't be a profile name. Try something without slashes that doesn't start with a dot.")]
+    BadProfileName { name: String },
+    /// Thrown when a key name (e.g. for `--exit-key`) isn't understood by `events::parse_key`
+    #[error("I don't know the key {key:?}. Try a single character like q, esc, enter, tab, space, backspace, ctrl-c, alt-x, or f1 to f12.")]
+    BadKey { key: String },
+    /// Thrown when a config file setting (or the option overriding it) has a value that can't be used
+    #[error("{value:?} isn't a valid {name:?} setting.")]
+    BadSetting { name: String, value: String },
+    /// Thrown when the system clipboard can't be opened or written to
+    #[error("I couldn't use the clipboard: {message}")]
+    ClipboardError { message: String },
+    /// Thrown when `quoth sync` is used but the quoth directory isn't a git repository
+    #[error("{path:?} isn't a git repository. Run `git init` in it to sync quotes.")]
+    NotAGitRepository { path: String },
+    /// Thrown when trying to link a quote to itself with `quoth link`
+    #[error("Quote {index:?} already speaks to itself.")]
+    SelfLink { index: usize },
     /// Thrown when $HOME is not set
     #[error("$HOME not set")]
     Homeless,
-    /// Thrown when badly formatted tsv file given for parsing
-    #[error("I can't read {filename:?}. Make sure it has 'Quote', 'Book', and 'Author' columns and is tab-separated.")]
+    /// Thrown when badly formatted tsv/csv file given for parsing
+    #[error("I can't read {filename:?}. Make sure it has 'Quote', 'Book', and 'Author' columns and is tab/comma-separated.")]
     FileParseError { filename: String },
 }
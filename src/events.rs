@@ -0,0 +1,131 @@
+use std::io;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Error;
+use termion::event::Key;
+use termion::input::TermRead;
+
+use quoth::errors::QuothError;
+
+pub enum Event<I> {
+    Input(I),
+    Tick,
+}
+
+/// A small event handler that wraps termion input and tick events. Each event
+/// type is handled in its own thread and returned to a common `Receiver`
+/// (the threads stop once the `Events` and its `Receiver` are dropped)
+pub struct Events {
+    rx: mpsc::Receiver<Event<Key>>,
+    input_handle: thread::JoinHandle<()>,
+    tick_handle: thread::JoinHandle<()>,
+}
+
+/// Parses a key spelled as a single character (`q`), `esc`, `enter`, `tab`, `space`, `backspace`,
+/// `ctrl-<char>`, `alt-<char>`, or `f1` to `f12` (names are case-insensitive, single characters aren't)
+pub fn parse_key(spelling: &str) -> Result<Key, Error> {
+    let bad_key = || QuothError::BadKey {
+        key: spelling.into(),
+    };
+    let mut chars = spelling.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Char(c));
+    }
+    let name = spelling.to_lowercase();
+    let single_char = |rest: &str| {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(bad_key()),
+        }
+    };
+    Ok(match name.as_str() {
+        "esc" | "escape" => Key::Esc,
+        "enter" | "return" => Key::Char('\n'),
+        "tab" => Key::Char('\t'),
+        "space" => Key::Char(' '),
+        "backspace" => Key::Backspace,
+        _ if name.starts_with("ctrl-") => Key::Ctrl(single_char(&name[5..])?),
+        _ if name.starts_with("alt-") => Key::Alt(single_char(&spelling[4..])?),
+        _ if name.starts_with('f') => match name[1..].parse::<u8>() {
+            Ok(n) if (1..=12).contains(&n) => Key::F(n),
+            _ => return Err(bad_key().into()),
+        },
+        _ => return Err(bad_key().into()),
+    })
+}
+
+/// Spells out a key the way `parse_key` reads it
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Esc => "esc".into(),
+        Key::Char('\n') => "enter".into(),
+        Key::Char('\t') => "tab".into(),
+        Key::Char(' ') => "space".into(),
+        Key::Char(c) => c.to_string(),
+        Key::Backspace => "backspace".into(),
+        Key::Ctrl(c) => format!("ctrl-{}", c),
+        Key::Alt(c) => format!("alt-{}", c),
+        Key::F(n) => format!("f{}", n),
+        key => format!("{:?}", key).to_lowercase(),
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    pub exit_key: Key,
+    pub tick_rate: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            exit_key: Key::Char('q'),
+            tick_rate: Duration::from_millis(250),
+        }
+    }
+}
+
+impl Events {
+    pub fn new() -> Events {
+        Events::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Events {
+        let (tx, rx) = mpsc::channel();
+        let input_handle = {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let stdin = io::stdin();
+                // Every key is passed on (even the exit key, which can be typed while searching in `browse`)
+                for evt in stdin.keys() {
+                    if let Ok(key) = evt {
+                        if tx.send(Event::Input(key)).is_err() {
+                            return;
+                        }
+                    }
+                }
+            })
+        };
+        let tick_handle = {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let tx = tx.clone();
+                while tx.send(Event::Tick).is_ok() {
+                    thread::sleep(config.tick_rate);
+                }
+            })
+        };
+        Events {
+            rx,
+            input_handle,
+            tick_handle,
+        }
+    }
+
+    pub fn next(&self) -> Result<Event<Key>, mpsc::RecvError> {
+        self.rx.recv()
+    }
+}
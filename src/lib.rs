@@ -0,0 +1,34 @@
+//! Record, retrieve, search, and categorize quotes from books
+//!
+//! The quote database can be used without the command-line interface:
+//! ```no_run
+//! use quoth::{get_quoth_dir, Quote, Trees};
+//!
+//! let mut trees = Trees::read(&get_quoth_dir()?)?;
+//! let index = trees.get_quote_index()?;
+//! let quote = Quote::new(
+//!     index,
+//!     "Mort",
+//!     "Terry Pratchett",
+//!     "death,fantasy",
+//!     chrono::Utc::now(),
+//!     "There is no justice. There is just me.".into(),
+//! );
+//! trees.add_quote(&quote)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+#![feature(inner_deref)]
+#![allow(dead_code)]
+#[macro_use]
+extern crate serde_derive;
+
+pub mod config;
+pub mod errors;
+pub mod quoth;
+pub mod utils;
+
+pub use crate::errors::QuothError;
+pub use crate::quoth::database::Trees;
+pub use crate::quoth::quotes::{PrintStyle, Quote};
+pub use crate::quoth::theme::Theme;
+pub use crate::quoth::{change_quoth_dir, get_quoth_dir};
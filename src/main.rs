@@ -2,24 +2,16 @@
 #![allow(dead_code)]
 #[macro_use]
 extern crate clap;
-#[macro_use]
-extern crate serde_derive;
 
 use anyhow::Error;
 use clap::App;
 
-use crate::quoth::Quoth;
+use crate::cli::Quoth;
 
-mod config;
-mod errors;
-mod quoth;
-mod utils;
+mod cli;
+mod events;
 
 fn main() -> Result<(), Error> {
-    //    utils::read_quotes_database(
-    //        "/Users/janani/Downloads/quotesdrivedb.csv",
-    //        "quotes_full_database.json",
-    //    )?;
     let yaml = load_yaml!("quoth.yml");
     let matches = App::from_yaml(yaml).get_matches();
     Quoth::start(matches)?;
@@ -1,31 +1,350 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::slice;
 use std::str;
 
 use anyhow::Error;
 use chrono::{Date, Datelike, DateTime, Utc};
 use path_abs::{PathDir, PathOps};
 use sled;
+use sled::transaction::{
+    ConflictableTransactionError, TransactionError, TransactionalTree, UnabortableTransactionError,
+};
+use sled::Transactional;
 
 use crate::config;
 use crate::errors::QuothError;
 use crate::quoth::quotes::Quote;
+use crate::quoth::stats::{AuthorCounts, MonthCounts, Stats, Totals};
 use crate::utils;
 
-/// If key exists, add value to existing values - join with a semicolon
-fn merge_index(_key: &[u8], old_indices: Option<&[u8]>, new_index: &[u8]) -> Option<Vec<u8>> {
-    let mut ret = old_indices
-        .map(|old| old.to_vec())
-        .unwrap_or_else(|| vec![]);
-    if !ret.is_empty() {
-        ret.extend_from_slice(&[utils::SEMICOLON]);
+/// Appends values to the `utils::SEPARATOR`-separated list stored under a key of a tree
+/// (values already in the list are skipped)
+fn extend_values(tree: &TransactionalTree, key: &[u8], values: &[String]) -> Result<(), Error> {
+    let mut key_values = match tree.get(key)? {
+        Some(old_values) => utils::split_values_string(&old_values)?,
+        None => Vec::new(),
+    };
+    let mut seen: HashSet<String> = key_values.iter().cloned().collect();
+    for value in values {
+        if seen.insert(value.clone()) {
+            key_values.push(value.clone());
+        }
+    }
+    tree.insert(
+        key,
+        key_values
+            .join(str::from_utf8(&[utils::SEPARATOR])?)
+            .as_bytes(),
+    )?;
+    Ok(())
+}
+
+/// Appends values to the lists stored under each key of a tree (see `extend_values`)
+fn extend_linkage_tree(
+    tree: &TransactionalTree,
+    new_values: &HashMap<String, Vec<String>>,
+) -> Result<(), Error> {
+    for (key, values) in new_values {
+        extend_values(tree, key.as_bytes(), values)?;
+    }
+    Ok(())
+}
+
+/// Removes a value from the `utils::SEPARATOR`-separated list stored under a key of a tree
+/// (along with the key if it was the last value), returns false if the key isn't in the tree
+fn remove_value(tree: &TransactionalTree, key: &[u8], value: &str) -> Result<bool, Error> {
+    let new_values: Vec<_> = match tree.get(key)? {
+        Some(values) => utils::split_values_string(&values)?
+            .into_iter()
+            .filter(|value_i| value_i != value)
+            .collect(),
+        None => return Ok(false),
+    };
+    if new_values.is_empty() {
+        tree.remove(key)?;
+    } else {
+        tree.insert(
+            key,
+            new_values
+                .join(str::from_utf8(&[utils::SEPARATOR])?)
+                .as_bytes(),
+        )?;
+    }
+    Ok(true)
+}
+
+/// Reads the quote index stored in the metadata tree (0 if no quotes were added yet)
+fn parse_quote_index(index: Option<sled::IVec>) -> Result<usize, Error> {
+    match index {
+        Some(index) => Ok(str::from_utf8(&index)?.parse::<usize>()?),
+        None => Ok(0),
+    }
+}
+
+/// Key of a quote in `Trees::hash_quote_tree` (`Quote::duplicate_hash` in hex)
+fn hash_key(quote: &Quote) -> String {
+    format!("{:016x}", quote.duplicate_hash())
+}
+
+/// Reads a tree of keys mapped to `utils::SEPARATOR`-separated values (indices, books, authors) into sets
+fn read_linkage_tree(tree: &sled::Tree) -> Result<HashMap<String, HashSet<String>>, Error> {
+    tree.iter()
+        .map(|item| {
+            let (key, values) = item.map_err(|_| QuothError::OutOfCheeseError {
+                message: "sled PageCache Error".into(),
+            })?;
+            Ok((
+                utils::u8_to_str(&key)?,
+                utils::split_values_string(&values)?.into_iter().collect(),
+            ))
+        })
+        .collect()
+}
+
+/// Reads the keys of a tree (authors, books, tags) as strings
+fn read_tree_keys(tree: &sled::Tree) -> Result<Vec<String>, Error> {
+    tree.iter()
+        .keys()
+        .map(|key| {
+            utils::u8_to_str(&key.map_err(|_| QuothError::OutOfCheeseError {
+                message: "sled PageCache Error".into(),
+            })?)
+        })
+        .collect()
+}
+
+/// Total size in bytes of the files in a directory (and its subdirectories)
+fn dir_size(dir: &Path) -> Result<u64, Error> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Copies every tree of one database into another, one batch per tree
+/// (unlike `sled::Db::import`, keys already in `to` are overwritten instead of panicking)
+fn copy_trees(from: &sled::Db, to: &sled::Db) -> Result<(), Error> {
+    for name in from.tree_names() {
+        let mut batch = sled::Batch::default();
+        for item in from.open_tree(&name)?.iter() {
+            let (key, value) = item?;
+            batch.insert(key, value);
+        }
+        to.open_tree(&name)?.apply_batch(batch)?;
+    }
+    Ok(())
+}
+
+/// Links of quotes to their authors, books, tags, words, and hash, gathered to be added to the linkage trees
+/// (by `Transaction::add_links`)
+#[derive(Default)]
+struct QuoteLinks {
+    author_quotes: HashMap<String, Vec<String>>,
+    author_books: HashMap<String, Vec<String>>,
+    book_quotes: HashMap<String, Vec<String>>,
+    book_authors: HashMap<String, String>,
+    tag_quotes: HashMap<String, Vec<String>>,
+    word_quotes: HashMap<String, Vec<String>>,
+    hash_quotes: HashMap<String, Vec<String>>,
+}
+
+impl QuoteLinks {
+    fn new(quotes: &[Quote]) -> Self {
+        let mut links = QuoteLinks::default();
+        for quote in quotes {
+            let index = quote.index.to_string();
+            for author in &quote.authors {
+                links
+                    .author_quotes
+                    .entry(author.clone())
+                    .or_insert_with(Vec::new)
+                    .push(index.clone());
+                links
+                    .author_books
+                    .entry(author.clone())
+                    .or_insert_with(Vec::new)
+                    .push(quote.book.clone());
+            }
+            links
+                .book_quotes
+                .entry(quote.book.clone())
+                .or_insert_with(Vec::new)
+                .push(index.clone());
+            links
+                .book_authors
+                .insert(quote.book.clone(), quote.author_names());
+            for tag in quote.tags.iter().map(|tag| utils::normalize_tag(tag)) {
+                if !tag.is_empty() {
+                    links
+                        .tag_quotes
+                        .entry(tag)
+                        .or_insert_with(Vec::new)
+                        .push(index.clone());
+                }
+            }
+            for word in utils::split_words(&quote.to_string()) {
+                links
+                    .word_quotes
+                    .entry(word)
+                    .or_insert_with(Vec::new)
+                    .push(index.clone());
+            }
+            links
+                .hash_quotes
+                .entry(hash_key(quote))
+                .or_insert_with(Vec::new)
+                .push(index.clone());
+        }
+        links
+    }
+}
+
+/// The trees quotes are stored and linked in, as seen from inside `Trees::transaction`
+/// (nothing written to them is seen outside until the transaction is committed)
+struct Transaction {
+    quote: TransactionalTree,
+    metadata: TransactionalTree,
+    author_quote: TransactionalTree,
+    author_book: TransactionalTree,
+    book_quote: TransactionalTree,
+    book_author: TransactionalTree,
+    tag_quote: TransactionalTree,
+    word_quote: TransactionalTree,
+    hash_quote: TransactionalTree,
+    collection_quote: TransactionalTree,
+}
+
+impl Transaction {
+    /// Retrieve a quote by its index (None if there's no such quote)
+    fn find_quote(&self, index: usize) -> Result<Option<Quote>, Error> {
+        match self.quote.get(index.to_string().as_bytes())? {
+            Some(quote) => Ok(Some(Quote::from_bytes(&quote)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn get_quote(&self, index: usize) -> Result<Quote, Error> {
+        Ok(self
+            .find_quote(index)?
+            .ok_or(QuothError::QuoteNotFound { index })?)
+    }
+
+    /// Store a quote under its index (without linking it in the other trees)
+    fn insert_quote(&self, quote: &Quote) -> Result<(), Error> {
+        self.quote
+            .insert(quote.index.to_string().as_bytes(), quote.to_bytes()?)?;
+        Ok(())
+    }
+
+    fn remove_quote(&self, index: usize) -> Result<Quote, Error> {
+        Quote::from_bytes(
+            &self
+                .quote
+                .remove(index.to_string().as_bytes())?
+                .ok_or(QuothError::QuoteNotFound { index })?,
+        )
+    }
+
+    /// Store quotes and raise the quote index to the highest of them (without linking them in the other trees)
+    fn store_quotes(&self, quotes: &[Quote]) -> Result<(), Error> {
+        let mut quote_index = parse_quote_index(self.metadata.get("quote_index")?)?;
+        for quote in quotes {
+            self.insert_quote(quote)?;
+            quote_index = quote_index.max(quote.index);
+        }
+        self.metadata
+            .insert("quote_index", quote_index.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// Add quotes to the author, book, tag, word, and hash trees
+    fn add_links(&self, links: &QuoteLinks) -> Result<(), Error> {
+        extend_linkage_tree(&self.author_quote, &links.author_quotes)?;
+        extend_linkage_tree(&self.author_book, &links.author_books)?;
+        extend_linkage_tree(&self.book_quote, &links.book_quotes)?;
+        for (book, authors) in &links.book_authors {
+            self.book_author
+                .insert(book.as_bytes(), authors.as_bytes())?;
+        }
+        extend_linkage_tree(&self.tag_quote, &links.tag_quotes)?;
+        extend_linkage_tree(&self.word_quote, &links.word_quotes)?;
+        extend_linkage_tree(&self.hash_quote, &links.hash_quotes)?;
+        Ok(())
+    }
+
+    /// Remove a quote from the author, book, tag, word, and hash trees
+    /// (tags are also looked for as written, for quotes stored before tags were normalized)
+    fn remove_links(&self, quote: &Quote) -> Result<(), Error> {
+        let (index, book_key) = (quote.index.to_string(), quote.book.as_bytes());
+        for author in &quote.authors {
+            let author_key = author.as_bytes();
+            if !remove_value(&self.author_quote, author_key, &index)? {
+                return Err(QuothError::AuthorNotFound {
+                    author: author.clone(),
+                }
+                .into());
+            }
+            let author_indices = match self.author_quote.get(author_key)? {
+                Some(indices) => utils::split_indices_usize(&indices)?,
+                None => Vec::new(),
+            };
+            // The same title can belong to several authors, so check this author's remaining quotes
+            let book_indices = match self.book_quote.get(book_key)? {
+                Some(indices) => utils::split_indices_usize(&indices)?,
+                None => Vec::new(),
+            };
+            if author_indices.is_empty() {
+                self.author_book.remove(author_key)?;
+            } else if !author_indices
+                .iter()
+                .any(|index_i| book_indices.contains(index_i))
+            {
+                remove_value(&self.author_book, author_key, &quote.book)?;
+            }
+        }
+        if !remove_value(&self.book_quote, book_key, &index)? {
+            return Err(QuothError::BookNotFound {
+                book: quote.book.clone(),
+            }
+            .into());
+        }
+        if self.book_quote.get(book_key)?.is_none() {
+            self.book_author.remove(book_key)?;
+        }
+        for tag in &quote.tags {
+            let normalized_tag = utils::normalize_tag(tag);
+            if normalized_tag.is_empty() {
+                continue;
+            }
+            if !remove_value(&self.tag_quote, normalized_tag.as_bytes(), &index)?
+                && !remove_value(&self.tag_quote, tag.as_bytes(), &index)?
+            {
+                return Err(QuothError::TagNotFound { tag: tag.clone() }.into());
+            }
+        }
+        for word in utils::split_words(&quote.to_string()) {
+            remove_value(&self.word_quote, word.as_bytes(), &index)?;
+        }
+        remove_value(&self.hash_quote, hash_key(quote).as_bytes(), &index)?;
+        Ok(())
     }
-    ret.extend_from_slice(new_index);
-    Some(ret)
 }
 
 /// Stores linkage information between authors, books, tags and quotes, along with quoth metadata
 pub struct Trees {
     pub db: sled::Db,
+    /// Whether tags are lowercased when quotes are stored (they're looked up whatever their case either way)
+    pub lowercase_tags: bool,
 }
 
 impl Trees {
@@ -39,11 +358,9 @@ impl Trees {
         Ok(self.db.open_tree("quote")?)
     }
 
+    /// Highest quote index handed out so far (never decreases, so indices of deleted quotes aren't reused)
     pub fn get_quote_index(&self) -> Result<usize, Error> {
-        match self.db.get("quote_index")? {
-            Some(index) => Ok(str::from_utf8(&index)?.parse::<usize>()?),
-            None => Ok(0)
-        }
+        parse_quote_index(self.db.get("quote_index")?)
     }
 
     pub fn author_quote_tree(&self) -> Result<sled::Tree, Error> {
@@ -66,52 +383,269 @@ impl Trees {
         Ok(self.db.open_tree("tag_quote")?)
     }
 
-    /// Changes the location of all `sled` trees and the metadata file
-    pub fn relocate(old_quoth_dir: &PathDir, new_quoth_dir: &PathDir) -> Result<(), Error> {
-        let old_trees = Trees::read(old_quoth_dir)?.db.export();
-        let new_trees = Trees::read(new_quoth_dir)?;
-        new_trees.db.import(old_trees);
+    pub fn word_quote_tree(&self) -> Result<sled::Tree, Error> {
+        Ok(self.db.open_tree("word_quote")?)
+    }
+
+    /// Quotes under the hash of their authors, book, and normalized text (see `Quote::duplicate_hash`),
+    /// so duplicates are found without reading every quote
+    pub fn hash_quote_tree(&self) -> Result<sled::Tree, Error> {
+        Ok(self.db.open_tree("hash_quote")?)
+    }
+
+    /// Quotes in each collection made with `quoth collection add`
+    /// (unlike the other linkage trees, this can't be rebuilt from the quotes by `reindex`)
+    pub fn collection_quote_tree(&self) -> Result<sled::Tree, Error> {
+        Ok(self.db.open_tree("collection_quote")?)
+    }
+
+    /// Whether `word_quote_tree` covers every quote (databases from before the word index need a `reindex`)
+    pub fn has_word_index(&self) -> Result<bool, Error> {
+        Ok(self.db.get("word_index")?.is_some())
+    }
+
+    /// Changes the location of all `sled` trees (including the quote index)
+    /// (the old trees are only removed once all their quotes are found in the new location)
+    pub fn relocate(&self, old_quoth_dir: &PathDir, new_quoth_dir: &PathDir) -> Result<(), Error> {
+        let num_quotes = self.quote_tree()?.len();
+        let num_relocated = {
+            let new_trees = Trees::read(new_quoth_dir)?;
+            copy_trees(&self.db, &new_trees.db)?;
+            new_trees.db.flush()?;
+            new_trees.quote_tree()?.len()
+        };
+        if num_relocated != num_quotes {
+            Trees::clear(new_quoth_dir)?;
+            return Err(QuothError::RelocationError {
+                num_quotes,
+                num_relocated,
+            }
+            .into());
+        }
         Trees::clear(old_quoth_dir)?;
         Ok(())
     }
 
-    /// Reads `sled` trees and metadata file from the locations specified in config (makes new ones the first time)
+    /// Replaces the database with a fresh one filled by `fill`, which returns the number of quotes it should have
+    /// (the old database is only removed once all the quotes are found in the new one)
+    fn replace_db<F: FnOnce(&sled::Db) -> Result<usize, Error>>(
+        quoth_dir: &PathDir,
+        fill: F,
+    ) -> Result<(), Error> {
+        let (db_path, new_db_path, old_db_path) = (
+            quoth_dir.join(config::DB_PATH),
+            quoth_dir.join(config::COMPACT_DB_PATH),
+            quoth_dir.join(config::OLD_DB_PATH),
+        );
+        // Left over if quoth died while rewriting the database before
+        if let Ok(leftover_db) = PathDir::new(&new_db_path) {
+            leftover_db.remove_all()?;
+        }
+        {
+            let new_db = sled::open(&PathDir::create_all(&new_db_path)?)?;
+            let num_quotes = fill(&new_db)?;
+            new_db.flush()?;
+            if new_db.open_tree("quote")?.len() != num_quotes {
+                drop(new_db);
+                PathDir::new(&new_db_path)?.remove_all()?;
+                return Err(QuothError::OutOfCheeseError {
+                    message: "Rewritten database is missing quotes".into(),
+                }
+                .into());
+            }
+        }
+        fs::rename(&db_path, &old_db_path)?;
+        fs::rename(&new_db_path, &db_path)?;
+        PathDir::new(&old_db_path)?.remove_all()?;
+        Ok(())
+    }
+
+    /// Rewrites all `sled` trees into a fresh database to reclaim space left behind by deleted and changed quotes,
+    /// returns the size of the database in bytes before and after
+    pub fn compact(quoth_dir: &PathDir) -> Result<(u64, u64), Error> {
+        let db_path = quoth_dir.join(config::DB_PATH);
+        let size_before = dir_size(db_path.as_ref())?;
+        Trees::replace_db(quoth_dir, |new_db| {
+            let old_trees = Trees::read(quoth_dir)?;
+            new_db.import(old_trees.db.export());
+            Ok(old_trees.quote_tree()?.len())
+        })?;
+        Ok((size_before, dir_size(db_path.as_ref())?))
+    }
+
+    /// Moves a database written by `sled` 0.28 (used by earlier versions of quoth) into the current format
+    fn upgrade_sled(quoth_dir: &PathDir) -> Result<(), Error> {
+        Trees::replace_db(quoth_dir, |new_db| {
+            let old_db = old_sled::Db::open(quoth_dir.join(config::DB_PATH))?;
+            new_db.import(old_db.export());
+            Ok(old_db.open_tree("quote")?.len())
+        })
+    }
+
+    /// Reads `sled` trees from the locations specified in config (makes new ones the first time)
     pub fn read(quoth_dir: &PathDir) -> Result<Self, Error> {
-        let trees = Trees {
-            db: sled::Db::open(&PathDir::create_all(quoth_dir.join(config::DB_PATH))?)?
+        let db_path = PathDir::create_all(quoth_dir.join(config::DB_PATH))?;
+        let db = match sled::open(&db_path) {
+            // Databases from earlier versions of quoth are upgraded the first time they're opened
+            Err(sled::Error::Unsupported(_)) => {
+                Trees::upgrade_sled(quoth_dir)?;
+                sled::open(&db_path)?
+            }
+            db => db?,
         };
-        trees.author_book_tree()?.set_merge_operator(merge_index);
-        trees.author_quote_tree()?.set_merge_operator(merge_index);
-        trees.book_quote_tree()?.set_merge_operator(merge_index);
-        trees.tag_quote_tree()?.set_merge_operator(merge_index);
+        let mut trees = Trees {
+            db,
+            lowercase_tags: true,
+        };
+        trees.upgrade()?;
         Ok(trees)
     }
 
+    /// Runs `f` on all the trees in one transaction, so either everything it writes is stored
+    /// or (if it returns an error) nothing is
+    fn transaction<T, F: Fn(&Transaction) -> Result<T, Error>>(&self, f: F) -> Result<T, Error> {
+        let (
+            quote_tree,
+            author_quote_tree,
+            author_book_tree,
+            book_quote_tree,
+            book_author_tree,
+            tag_quote_tree,
+            word_quote_tree,
+            hash_quote_tree,
+            collection_quote_tree,
+        ) = (
+            self.quote_tree()?,
+            self.author_quote_tree()?,
+            self.author_book_tree()?,
+            self.book_quote_tree()?,
+            self.book_author_tree()?,
+            self.tag_quote_tree()?,
+            self.word_quote_tree()?,
+            self.hash_quote_tree()?,
+            self.collection_quote_tree()?,
+        );
+        let result = (
+            &quote_tree,
+            &*self.db,
+            &author_quote_tree,
+            &author_book_tree,
+            &book_quote_tree,
+            &book_author_tree,
+            &tag_quote_tree,
+            &word_quote_tree,
+            &hash_quote_tree,
+            &collection_quote_tree,
+        )
+            .transaction(
+                |(
+                    quote,
+                    metadata,
+                    author_quote,
+                    author_book,
+                    book_quote,
+                    book_author,
+                    tag_quote,
+                    word_quote,
+                    hash_quote,
+                    collection_quote,
+                )| {
+                    f(&Transaction {
+                        quote: quote.clone(),
+                        metadata: metadata.clone(),
+                        author_quote: author_quote.clone(),
+                        author_book: author_book.clone(),
+                        book_quote: book_quote.clone(),
+                        book_author: book_author.clone(),
+                        tag_quote: tag_quote.clone(),
+                        word_quote: word_quote.clone(),
+                        hash_quote: hash_quote.clone(),
+                        collection_quote: collection_quote.clone(),
+                    })
+                    .map_err(|error| {
+                        // sled's own errors are passed back to it, so conflicting transactions are retried
+                        match error.downcast::<UnabortableTransactionError>() {
+                            Ok(error) => error.into(),
+                            Err(error) => ConflictableTransactionError::Abort(error),
+                        }
+                    })
+                },
+            );
+        match result {
+            Ok(value) => Ok(value),
+            Err(TransactionError::Abort(error)) => Err(error),
+            Err(TransactionError::Storage(error)) => Err(error.into()),
+        }
+    }
 
-    /// Add an author and a book to the trees
-    fn add_author_and_book(
-        &mut self,
-        author_key: &[u8],
-        book_key: &[u8],
-        index_key: &[u8],
-    ) -> Result<(), Error> {
-        self.author_quote_tree()?
-            .merge(author_key.to_vec(), index_key.to_vec())?;
-        let author_book_tree = self.author_book_tree()?;
-        if let Some(books) = author_book_tree.get(author_key)? {
-            if !utils::split_values_string(&books)?.contains(&utils::u8_to_str(book_key)?) {
-                author_book_tree.merge(author_key.to_vec(), book_key.to_vec())?;
+    /// Brings trees written by earlier versions of quoth up to date (run by `read`)
+    fn upgrade(&mut self) -> Result<(), Error> {
+        if self.quote_tree()?.is_empty() {
+            self.db.insert("word_index", "")?;
+        }
+        // Databases from before the hash index get one the first time they're opened
+        if self.db.get("hash_index")?.is_none() {
+            self.build_hash_index()?;
+        }
+        // Quotes without tags used to be stored under an empty tag
+        self.tag_quote_tree()?.remove("")?;
+        // Values used to be separated by semicolons, which broke on book titles with semicolons
+        if self.db.get("separator")?.is_none() {
+            if !self.quote_tree()?.is_empty() {
+                self.reindex()?;
             }
+            self.db.insert("separator", &[utils::SEPARATOR])?;
+        }
+        // Tag keys used to keep their case if tags weren't lowercased
+        if read_tree_keys(&self.tag_quote_tree()?)?
+            .iter()
+            .any(|tag| *tag != utils::normalize_tag(tag))
+        {
+            self.reindex()?;
+        }
+        Ok(())
+    }
+
+    /// How a tag is stored on quotes: cleaned, and lowercased if `lowercase_tags` is set (see `utils::clean_tag`)
+    pub fn stored_tag(&self, tag: &str) -> String {
+        if self.lowercase_tags {
+            utils::normalize_tag(tag)
         } else {
-            author_book_tree.insert(author_key.to_vec(), book_key.to_vec())?;
+            utils::clean_tag(tag)
         }
-        self.book_quote_tree()?
-            .merge(book_key.to_vec(), index_key.to_vec())?;
-        self.book_author_tree()?
-            .insert(book_key.to_vec(), author_key.to_vec())?;
+    }
+
+    /// `quote` with its tags as they're stored (see `stored_tag`, tags that become repeats are dropped)
+    fn stored_quote<'q>(&self, quote: &'q Quote) -> Cow<'q, Quote> {
+        let tags = utils::dedup_tags(quote.tags.iter().map(|tag| self.stored_tag(tag)));
+        if tags == quote.tags {
+            Cow::Borrowed(quote)
+        } else {
+            Cow::Owned(Quote {
+                tags,
+                ..quote.clone()
+            })
+        }
+    }
+
+    /// Fill the hash-quote tree from the quotes in `quote_tree` in one transaction
+    fn build_hash_index(&mut self) -> Result<(), Error> {
+        self.hash_quote_tree()?.clear()?;
+        let mut hash_quotes = HashMap::new();
+        for quote in
+            self.list_quotes_in_date_range(utils::date_start(None), utils::date_end(None))?
+        {
+            hash_quotes
+                .entry(hash_key(&quote))
+                .or_insert_with(Vec::new)
+                .push(quote.index.to_string());
+        }
+        self.transaction(|transaction| extend_linkage_tree(&transaction.hash_quote, &hash_quotes))?;
+        self.db.insert("hash_index", "")?;
         Ok(())
     }
 
+    /// Retrieve a quote by its index
     pub fn get_quote(&self, index: usize) -> Result<Quote, Error> {
         let index_key = index.to_string();
         let index_key = index_key.as_bytes();
@@ -126,25 +660,60 @@ impl Trees {
         indices.iter().map(|i| self.get_quote(*i)).collect()
     }
 
+    /// Quote with the lowest (or, if `last`, highest) index or date (`field` is "index" or "date"),
+    /// quotes on the same date are ordered by index
+    pub fn get_end_quote(&self, field: &str, last: bool) -> Result<Quote, Error> {
+        let by_date = match field {
+            "index" => false,
+            "date" => true,
+            _ => {
+                return Err(QuothError::OutOfCheeseError {
+                    message: format!("Can't order quotes by {:?}", field),
+                }
+                .into())
+            }
+        };
+        let key = |quote: &Quote| (if by_date { Some(quote.date) } else { None }, quote.index);
+        let quotes =
+            self.list_quotes_in_date_range(utils::date_start(None), utils::date_end(None))?;
+        let end_quote = if last {
+            quotes.into_iter().max_by_key(key)
+        } else {
+            quotes.into_iter().min_by_key(key)
+        };
+        end_quote.ok_or_else(|| QuothError::NoQuotesFound.into())
+    }
+
     /// List quotes in date range
     pub fn list_quotes_in_date_range(
         &self,
         from_date: DateTime<Utc>,
         to_date: DateTime<Utc>,
     ) -> Result<Vec<Quote>, Error> {
-        Ok(self
-            .quote_tree()?
-            .iter()
-            .map(|item| {
-                item.map_err(|_| QuothError::OutOfCheeseError {
-                    message: "sled PageCache Error".into(),
-                }.into())
-                    .and_then(|(_, quote)| Quote::from_bytes(&quote))
-            })
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .filter(|quote| quote.in_date_range(from_date, to_date))
-            .collect())
+        self.iter_quotes_in_date_range(from_date, to_date)?
+            .collect()
+    }
+
+    /// Lazily iterate over quotes in date range (quotes are read from the database one at a time)
+    pub fn iter_quotes_in_date_range(
+        &self,
+        from_date: DateTime<Utc>,
+        to_date: DateTime<Utc>,
+    ) -> Result<impl Iterator<Item = Result<Quote, Error>>, Error> {
+        Ok(self.quote_tree()?.iter().filter_map(move |item| {
+            match item
+                .map_err(|_| {
+                    QuothError::OutOfCheeseError {
+                        message: "sled PageCache Error".into(),
+                    }
+                    .into()
+                })
+                .and_then(|(_, quote)| Quote::from_bytes(&quote))
+            {
+                Ok(ref quote) if !quote.in_date_range(from_date, to_date) => None,
+                result => Some(result),
+            }
+        }))
     }
 
     pub fn increment_quote_index(&mut self) -> Result<(), Error> {
@@ -153,170 +722,265 @@ impl Trees {
     }
 
     /// Add a Quote (with all attached data) to the trees and change metadata accordingly
+    /// (in one transaction, so an interrupted add leaves the trees as they were)
     pub fn add_quote(&mut self, quote: &Quote) -> Result<usize, Error> {
-        let author_key = quote.author.as_bytes();
-        let book_key = quote.book.as_bytes();
-        let index_key = quote.index.to_string();
-        let index_key = index_key.as_bytes();
-        self.quote_tree()?.insert(index_key, quote.to_bytes()?)?;
-        self.add_author_and_book(author_key, book_key, index_key)?;
-        for tag in &quote.tags {
-            let tag_key = tag.as_bytes();
-            self.tag_quote_tree()?
-                .merge(tag_key.to_vec(), index_key.to_vec())?;
-        }
-        self.increment_quote_index()?;
+        self.add_quotes(slice::from_ref(quote))?;
         Ok(quote.index)
     }
 
-    /// Delete an author
-    fn delete_author(&mut self, author_key: &[u8]) -> Result<(), Error> {
-        self.author_quote_tree()?.remove(author_key)?;
-        let author = utils::u8_to_str(author_key)?;
-        let author_book_tree = self.author_book_tree()?;
-        let books = utils::split_values_string(
-            &author_book_tree
-                .get(author_key)?
-                .ok_or(QuothError::AuthorNotFound { author })?,
-        )?;
-        let mut book_quote_batch = sled::Batch::default();
-        let mut book_author_batch = sled::Batch::default();
-        for book in books {
-            let book_key = book.as_bytes();
-            book_author_batch.remove(book_key);
-            book_quote_batch.remove(book_key);
-        }
-        self.book_quote_tree()?.apply_batch(book_quote_batch)?;
-        self.book_author_tree()?.apply_batch(book_author_batch)?;
-        author_book_tree.remove(author_key)?;
-        Ok(())
+    /// Add many quotes at once, gathering their links before writing them all in one transaction
+    pub fn add_quotes(&mut self, quotes: &[Quote]) -> Result<usize, Error> {
+        let quotes: Vec<_> = quotes
+            .iter()
+            .map(|quote| self.stored_quote(quote).into_owned())
+            .collect();
+        let links = QuoteLinks::new(&quotes);
+        self.transaction(|transaction| {
+            transaction.store_quotes(&quotes)?;
+            transaction.add_links(&links)
+        })?;
+        Ok(quotes.len())
     }
 
-    /// Delete a quote index from the tag-quote tree
-    fn delete_from_tag(
+    /// Delete a quote (and all associated data) from the trees and metadata
+    /// (in one transaction, along with taking it out of collections and its related quotes' links)
+    pub fn delete_quote(&mut self, index: usize) -> Result<(), Error> {
+        let (index_key, collections) = (
+            index.to_string(),
+            read_tree_keys(&self.collection_quote_tree()?)?,
+        );
+        self.transaction(|transaction| {
+            let quote = transaction.remove_quote(index)?;
+            transaction.remove_links(&quote)?;
+            for name in &collections {
+                remove_value(&transaction.collection_quote, name.as_bytes(), &index_key)?;
+            }
+            for related_index in &quote.related {
+                // Links to quotes that were deleted before are left alone
+                if let Some(mut related_quote) = transaction.find_quote(*related_index)? {
+                    related_quote.related.retain(|i| *i != index);
+                    related_quote.modified = Utc::now();
+                    transaction.insert_quote(&related_quote)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Records each of two quotes in the other's related quotes (returns false if they were already linked)
+    pub fn link_quotes(&mut self, index_1: usize, index_2: usize) -> Result<bool, Error> {
+        if index_1 == index_2 {
+            return Err(QuothError::SelfLink { index: index_1 }.into());
+        }
+        let (mut quote_1, mut quote_2) = (self.get_quote(index_1)?, self.get_quote(index_2)?);
+        if quote_1.related.contains(&index_2) && quote_2.related.contains(&index_1) {
+            return Ok(false);
+        }
+        if !quote_1.related.contains(&index_2) {
+            quote_1.related.push(index_2);
+            self.change_quote(index_1, &quote_1)?;
+        }
+        if !quote_2.related.contains(&index_1) {
+            quote_2.related.push(index_1);
+            self.change_quote(index_2, &quote_2)?;
+        }
+        Ok(true)
+    }
+
+    /// Change a stored quote's information, updating its `modified` time
+    /// (the quote and its links are changed in one transaction)
+    pub fn change_quote(
         &mut self,
-        tag_key: &[u8],
         index: usize,
-        batch: &mut sled::Batch,
+        new_quote: &Quote,
     ) -> Result<(), Error> {
-        let tag = utils::u8_to_str(tag_key)?;
-        let new_indices: Vec<_> = utils::split_indices_usize(
-            &self
-                .tag_quote_tree()?
-                .get(tag_key)?
-                .ok_or(QuothError::TagNotFound { tag })?,
-        )?
-        .into_iter()
-        .filter(|index_i| *index_i != index)
-        .collect();
-        if new_indices.is_empty() {
-            batch.remove(tag_key);
-        } else {
-            batch.insert(tag_key.to_vec(), utils::make_indices_string(&new_indices)?);
+        let new_quote = self.stored_quote(new_quote).into_owned();
+        self.transaction(|transaction| {
+            let old_quote = transaction.get_quote(index)?;
+            // A changed quote keeps the time it was added
+            let new_quote = Quote {
+                index,
+                created: old_quote.created,
+                modified: Utc::now(),
+                ..new_quote.clone()
+            };
+            transaction.remove_links(&old_quote)?;
+            transaction.insert_quote(&new_quote)?;
+            transaction.add_links(&QuoteLinks::new(slice::from_ref(&new_quote)))
+        })
+    }
+
+    /// Rename a tag on all its quotes (merging into `new_tag` if it exists), returns the number of quotes changed
+    /// (quotes are found whatever the case and spacing of `old_tag`, see `utils::normalize_tag`)
+    pub fn rename_tag(&mut self, old_tag: &str, new_tag: &str) -> Result<usize, Error> {
+        let (old_tag, new_tag) = (utils::normalize_tag(old_tag), self.stored_tag(new_tag));
+        let indices = self.get_tag_quotes(&old_tag)?;
+        for index in &indices {
+            let mut quote = self.get_quote(*index)?;
+            quote.tags = utils::dedup_tags(quote.tags.drain(..).map(|tag| {
+                if utils::normalize_tag(&tag) == old_tag {
+                    new_tag.clone()
+                } else {
+                    tag
+                }
+            }));
+            self.change_quote(*index, &quote)?;
         }
-        Ok(())
+        Ok(indices.len())
     }
 
-    /// Delete a quote index from the book-quote tree
-    fn delete_from_book(&mut self, book_key: &[u8], index: usize) -> Result<(), Error> {
-        let book = utils::u8_to_str(book_key)?;
-        let new_indices: Vec<_> = utils::split_indices_usize(
-            &self
-                .book_quote_tree()?
-                .get(book_key)?
-                .ok_or(QuothError::BookNotFound { book })?,
-        )?
-        .into_iter()
-        .filter(|index_i| *index_i != index)
-        .collect();
-        if new_indices.is_empty() {
-            self.book_quote_tree()?.remove(book_key)?;
-            self.book_author_tree()?.remove(book_key)?;
-        } else {
-            self.book_quote_tree()?
-                .insert(book_key.to_vec(), utils::make_indices_string(&new_indices)?)?;
+    /// Rename an author on all their quotes (merging into `new_author` if they exist), returns the number of quotes changed
+    pub fn rename_author(&mut self, old_author: &str, new_author: &str) -> Result<usize, Error> {
+        let (old_author, new_author) = (
+            utils::camel_case_phrase(old_author),
+            utils::camel_case_phrase(new_author),
+        );
+        let indices = self.get_author_quotes(&old_author)?;
+        for index in &indices {
+            let mut quote = self.get_quote(*index)?;
+            let mut authors: Vec<String> = Vec::with_capacity(quote.authors.len());
+            for author in quote.authors.drain(..) {
+                let author = if author == old_author {
+                    new_author.clone()
+                } else {
+                    author
+                };
+                if !authors.contains(&author) {
+                    authors.push(author);
+                }
+            }
+            quote.authors = authors;
+            self.change_quote(*index, &quote)?;
         }
-        Ok(())
+        Ok(indices.len())
     }
 
-    /// Delete a quote index from the author and book trees
-    fn delete_from_author_and_book(
-        &mut self,
-        author_key: &[u8],
-        book_key: &[u8],
-        index: usize,
-    ) -> Result<(), Error> {
-        let author = utils::u8_to_str(author_key)?;
-        let new_indices: Vec<_> = utils::split_indices_usize(
-            &self
-                .author_quote_tree()?
-                .get(author_key)?
-                .ok_or(QuothError::AuthorNotFound { author })?,
-        )?
-        .into_iter()
-        .filter(|index_i| *index_i != index)
-        .collect();
-        if new_indices.is_empty() {
-            self.delete_author(author_key)?;
-        } else {
-            self.author_quote_tree()?.insert(
-                author_key.to_vec(),
-                utils::make_indices_string(&new_indices)?,
-            )?;
-            self.delete_from_book(book_key, index)?;
+    /// Rename a book on all its quotes (whichever author they're by), returns the number of quotes changed
+    pub fn rename_book(&mut self, old_book: &str, new_book: &str) -> Result<usize, Error> {
+        let indices = self.get_book_quotes(old_book)?;
+        for index in &indices {
+            let mut quote = self.get_quote(*index)?;
+            quote.book = utils::camel_case_phrase(new_book);
+            self.change_quote(*index, &quote)?;
         }
-        Ok(())
+        Ok(indices.len())
     }
 
-    fn remove_quote(&mut self, index: usize) -> Result<Quote, Error> {
-        let index_key = index.to_string();
-        let index_key = index_key.as_bytes();
-        Ok(Quote::from_bytes(
-            &self.quote_tree()?
-                .remove(index_key)?
-                .ok_or(QuothError::QuoteNotFound { index })?,
+    /// Finds a stored quote (other than `quote` itself) with the same authors, book, and text as `quote`
+    /// (see `Quote::duplicate_key`), looked up by hash in `hash_quote_tree`
+    pub fn find_duplicate(&self, quote: &Quote) -> Result<Option<usize>, Error> {
+        let indices = match self.hash_quote_tree()?.get(hash_key(quote).as_bytes())? {
+            Some(indices) => utils::split_indices_usize(&indices)?,
+            None => return Ok(None),
+        };
+        // Different quotes can (rarely) share a hash
+        let key = quote.duplicate_key();
+        for index in indices {
+            if index != quote.index && self.get_quote(index)?.duplicate_key() == key {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Groups of quotes with the same authors, book, and text (see `Quote::duplicate_key`), each sorted by index
+    pub fn get_duplicate_groups(&self) -> Result<Vec<Vec<usize>>, Error> {
+        let mut duplicate_groups = Vec::new();
+        for item in self.hash_quote_tree()?.iter() {
+            let (_, indices) = item.map_err(|_| QuothError::OutOfCheeseError {
+                message: "sled PageCache Error".into(),
+            })?;
+            let indices = utils::split_indices_usize(&indices)?;
+            if indices.len() < 2 {
+                continue;
+            }
+            // Different quotes can (rarely) share a hash
+            let mut groups: HashMap<(Vec<String>, String, String), Vec<usize>> = HashMap::new();
+            for index in indices {
+                groups
+                    .entry(self.get_quote(index)?.duplicate_key())
+                    .or_default()
+                    .push(index);
+            }
+            duplicate_groups.extend(groups.into_values().filter_map(|mut group| {
+                if group.len() > 1 {
+                    group.sort();
+                    Some(group)
+                } else {
+                    None
+                }
+            }));
+        }
+        duplicate_groups.sort();
+        Ok(duplicate_groups)
+    }
+
+    /// Lists all authors (as stored, i.e. camel-cased)
+    pub fn get_authors(&self) -> Result<Vec<String>, Error> {
+        read_tree_keys(&self.author_quote_tree()?)
+    }
+
+    /// Lists all tags (as stored, i.e. normalized)
+    pub fn get_tags(&self) -> Result<Vec<String>, Error> {
+        read_tree_keys(&self.tag_quote_tree()?)
+    }
+
+    /// Adds quotes to a collection (making it if it doesn't exist), skipping quotes already in it
+    pub fn add_to_collection(&mut self, name: &str, indices: &[usize]) -> Result<(), Error> {
+        for index in indices {
+            self.get_quote(*index)?;
+        }
+        let indices: Vec<_> = indices.iter().map(|index| index.to_string()).collect();
+        self.transaction(|transaction| {
+            extend_values(&transaction.collection_quote, name.as_bytes(), &indices)
+        })
+    }
+
+    /// Retrieve the quotes in a collection (in the order they were added)
+    pub fn get_collection_quotes(&self, name: &str) -> Result<Vec<usize>, Error> {
+        utils::split_indices_usize(&self.collection_quote_tree()?.get(name.as_bytes())?.ok_or(
+            QuothError::CollectionNotFound {
+                name: name.to_owned(),
+            },
         )?)
     }
-    /// Delete a quote (and all associated data) from the trees and metadata
-    pub fn delete_quote(&mut self, index: usize) -> Result<(), Error> {
-        let quote = self.remove_quote(index)?;
-        let author_key = quote.author.as_bytes();
-        let book_key = quote.book.as_bytes();
-        self.delete_from_author_and_book(author_key, book_key, index)?;
-        let mut tag_batch = sled::Batch::default();
-        for tag in quote.tags {
-            self.delete_from_tag(tag.as_bytes(), index, &mut tag_batch)?;
-        }
-        self.tag_quote_tree()?.apply_batch(tag_batch)?;
-        Ok(())
+
+    /// Lists collections (sorted by name) with their number of quotes
+    pub fn get_collection_counts(&self) -> Result<Vec<(String, usize)>, Error> {
+        self.collection_quote_tree()?
+            .iter()
+            .map(|item| {
+                let (name, indices) = item.map_err(|_| QuothError::OutOfCheeseError {
+                    message: "sled PageCache Error".into(),
+                })?;
+                Ok((
+                    utils::u8_to_str(&name)?,
+                    utils::split_indices_usize(&indices)?.len(),
+                ))
+            })
+            .collect()
     }
 
-    /// Change a stored quote's information
-    pub fn change_quote(
-        &mut self,
-        index: usize,
-        new_quote: &Quote,
-    ) -> Result<(), Error> {
-        let old_quote = self.get_quote(index)?;
-        let (old_author_key, old_book_key) =
-            (old_quote.author.as_bytes(), old_quote.book.as_bytes());
-        self.delete_from_author_and_book(old_author_key, old_book_key, index)?;
-        let mut tag_batch = sled::Batch::default();
-        for tag in old_quote.tags {
-            self.delete_from_tag(tag.as_bytes(), index, &mut tag_batch)?;
-        }
-        self.tag_quote_tree()?.apply_batch(tag_batch)?;
-        let (author_key, book_key) = (new_quote.author.as_bytes(), new_quote.book.as_bytes());
-        let index_key = index.to_string();
-        let index_key = index_key.as_bytes();
-        self.add_author_and_book(author_key, book_key, index_key)?;
-        for tag in &new_quote.tags {
-            let tag_key = tag.as_bytes();
-            self.tag_quote_tree()?
-                .merge(tag_key.to_vec(), index_key.to_vec())?;
-        }
-        self.quote_tree()?
-            .insert(index_key, new_quote.to_bytes()?)?;
+    /// Changes the quote indices in every collection to `new_index(index)`, dropping those it gives None for
+    /// (and collections left empty)
+    fn remap_collections<F: Fn(usize) -> Option<usize>>(&self, new_index: F) -> Result<(), Error> {
+        let collection_quote_tree = self.collection_quote_tree()?;
+        let mut batch = sled::Batch::default();
+        for item in collection_quote_tree.iter() {
+            let (name, indices) = item.map_err(|_| QuothError::OutOfCheeseError {
+                message: "sled PageCache Error".into(),
+            })?;
+            let new_indices: Vec<_> = utils::split_indices_usize(&indices)?
+                .into_iter()
+                .filter_map(&new_index)
+                .collect();
+            if new_indices.is_empty() {
+                batch.remove(name);
+            } else {
+                batch.insert(name, utils::make_indices_string(&new_indices)?);
+            }
+        }
+        collection_quote_tree.apply_batch(batch)?;
         Ok(())
     }
 
@@ -346,11 +1010,14 @@ impl Trees {
 
     /// Retrieve quotes associated with a given tag
     pub fn get_tag_quotes(&self, tag: &str) -> Result<Vec<usize>, Error> {
-        utils::split_indices_usize(&self.tag_quote_tree()?.get(tag.as_bytes())?.ok_or(
-            QuothError::TagNotFound {
-                tag: tag.to_owned(),
-            },
-        )?)
+        utils::split_indices_usize(
+            &self
+                .tag_quote_tree()?
+                .get(utils::normalize_tag(tag).as_bytes())?
+                .ok_or(QuothError::TagNotFound {
+                    tag: tag.to_owned(),
+                })?,
+        )
     }
 
     pub fn get_quote_and_book_counts_per_month(
@@ -390,6 +1057,19 @@ impl Trees {
         Ok((quote_counts, book_counts))
     }
 
+    /// Get number of quotes recorded on each day between `from_date` and `to_date`
+    pub fn get_quote_counts_per_day(
+        &self,
+        from_date: DateTime<Utc>,
+        to_date: DateTime<Utc>,
+    ) -> Result<HashMap<Date<Utc>, u64>, Error> {
+        let mut quote_counts = HashMap::new();
+        for quote in self.iter_quotes_in_date_range(from_date, to_date)? {
+            *quote_counts.entry(quote?.date.date()).or_insert(0) += 1;
+        }
+        Ok(quote_counts)
+    }
+
     /// Get number of books and number of quotes per author for all authors stored
     pub fn get_author_counts(&self) -> Result<HashMap<String, (u64, u64)>, Error> {
         let author_books: HashMap<String, u64> = self
@@ -436,4 +1116,608 @@ impl Trees {
             })
             .collect())
     }
+
+    /// Collects quote and book counts per month (for quotes between `from_date` and `to_date`),
+    /// book and quote counts per author, and totals
+    pub fn get_stats(
+        &self,
+        from_date: DateTime<Utc>,
+        to_date: DateTime<Utc>,
+    ) -> Result<Stats, Error> {
+        let (quote_counts, book_counts) =
+            self.get_quote_and_book_counts_per_month(from_date, to_date)?;
+        let (min_date, max_date) = match (quote_counts.keys().min(), quote_counts.keys().max()) {
+            (Some(min_date), Some(max_date)) => (*min_date, *max_date),
+            _ => return Err(QuothError::NoQuotesFound.into()),
+        };
+        let months = utils::get_months(min_date, max_date)
+            .into_iter()
+            .map(|month| MonthCounts {
+                month: month.format("%Y-%m").to_string(),
+                quotes: *quote_counts.get(&month).unwrap_or(&0),
+                books: *book_counts.get(&month).unwrap_or(&0),
+            })
+            .collect();
+        let authors = self.get_author_table()?;
+        let (quotes, words) = (self.quote_tree()?.len(), self.get_word_count()?);
+        Ok(Stats {
+            months,
+            authors,
+            totals: Totals {
+                quotes,
+                books: self.book_quote_tree()?.len(),
+                authors: self.author_quote_tree()?.len(),
+                tags: self.get_tag_counts()?.len(),
+                words,
+                words_per_quote: utils::words_per_quote(words, quotes),
+            },
+        })
+    }
+
+    /// Get the total number of (whitespace-separated) words in all quotes stored
+    pub fn get_word_count(&self) -> Result<usize, Error> {
+        self.iter_quotes_in_date_range(utils::date_start(None), utils::date_end(None))?
+            .map(|quote| Ok(quote?.word_count()))
+            .sum()
+    }
+
+    /// Get number of books and number of quotes per author for all authors stored, sorted by author
+    pub fn get_author_table(&self) -> Result<Vec<AuthorCounts>, Error> {
+        let mut authors: Vec<_> = self
+            .get_author_counts()?
+            .into_iter()
+            .map(|(author, (books, quotes))| AuthorCounts {
+                author,
+                books,
+                quotes,
+            })
+            .collect();
+        AuthorCounts::sort_by_field(&mut authors, "name")?;
+        Ok(authors)
+    }
+
+    /// Get the authors (see `Quote::author_names`) and number of quotes for all books stored
+    pub fn get_book_counts(&self) -> Result<HashMap<String, (String, u64)>, Error> {
+        let book_quote_tree = self.book_quote_tree()?;
+        self.book_author_tree()?
+            .iter()
+            .map(|item| {
+                let (book, author) = item.map_err(|_| QuothError::OutOfCheeseError {
+                    message: "sled PageCache Error".into(),
+                })?;
+                let num_quotes = match book_quote_tree.get(&book)? {
+                    Some(quotes) => utils::split_indices_usize(&quotes)?.len() as u64,
+                    None => 0,
+                };
+                Ok((
+                    utils::u8_to_str(&book)?,
+                    (utils::u8_to_str(&author)?, num_quotes),
+                ))
+            })
+            .collect()
+    }
+
+    /// Retrieve quotes with a word containing `term` (which should be lowercase letters and digits)
+    pub fn get_word_quotes(&self, term: &str) -> Result<HashSet<usize>, Error> {
+        let mut indices = HashSet::new();
+        for item in self.word_quote_tree()?.iter() {
+            let (word, word_indices) = item.map_err(|_| QuothError::OutOfCheeseError {
+                message: "sled PageCache Error".into(),
+            })?;
+            if utils::u8_to_str(&word)?.contains(term) {
+                indices.extend(utils::split_indices_usize(&word_indices)?);
+            }
+        }
+        Ok(indices)
+    }
+
+    /// Get the number of quotes for all tags stored (skipping empty tags)
+    pub fn get_tag_counts(&self) -> Result<HashMap<String, u64>, Error> {
+        let mut tag_counts = HashMap::new();
+        for item in self.tag_quote_tree()?.iter() {
+            let (tag, quotes) = item.map_err(|_| QuothError::OutOfCheeseError {
+                message: "sled PageCache Error".into(),
+            })?;
+            let tag = utils::u8_to_str(&tag)?;
+            if tag.is_empty() {
+                continue;
+            }
+            tag_counts.insert(tag, utils::split_indices_usize(&quotes)?.len() as u64);
+        }
+        Ok(tag_counts)
+    }
+
+    /// Check that the author, book, tag, word, and hash trees agree with the quotes in `quote_tree`,
+    /// returns a description of each orphaned index or dangling/missing link found
+    pub fn check(&self) -> Result<Vec<String>, Error> {
+        let quotes =
+            self.list_quotes_in_date_range(utils::date_start(None), utils::date_end(None))?;
+        let indices: HashSet<String> = quotes.iter().map(|quote| quote.index.to_string()).collect();
+        let (
+            mut author_quotes,
+            mut author_books,
+            mut book_quotes,
+            mut book_authors,
+            mut tag_quotes,
+            mut word_quotes,
+            mut hash_quotes,
+        ) = (
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+        );
+        for quote in &quotes {
+            let index = quote.index.to_string();
+            for author in &quote.authors {
+                author_quotes
+                    .entry(author.clone())
+                    .or_insert_with(HashSet::new)
+                    .insert(index.clone());
+                author_books
+                    .entry(author.clone())
+                    .or_insert_with(HashSet::new)
+                    .insert(quote.book.clone());
+            }
+            book_quotes
+                .entry(quote.book.clone())
+                .or_insert_with(HashSet::new)
+                .insert(index.clone());
+            book_authors
+                .entry(quote.book.clone())
+                .or_insert_with(HashSet::new)
+                .insert(quote.author_names());
+            for tag in quote.tags.iter().map(|tag| utils::normalize_tag(tag)) {
+                if !tag.is_empty() {
+                    tag_quotes
+                        .entry(tag)
+                        .or_insert_with(HashSet::new)
+                        .insert(index.clone());
+                }
+            }
+            for word in utils::split_words(&quote.to_string()) {
+                word_quotes
+                    .entry(word)
+                    .or_insert_with(HashSet::new)
+                    .insert(index.clone());
+            }
+            hash_quotes
+                .entry(hash_key(quote))
+                .or_insert_with(HashSet::new)
+                .insert(index.clone());
+        }
+        let mut problems = Vec::new();
+        let mut linkage_trees = vec![
+            (
+                "author_quote",
+                &author_quotes,
+                self.author_quote_tree()?,
+                true,
+            ),
+            (
+                "author_book",
+                &author_books,
+                self.author_book_tree()?,
+                false,
+            ),
+            ("book_quote", &book_quotes, self.book_quote_tree()?, true),
+            (
+                "book_author",
+                &book_authors,
+                self.book_author_tree()?,
+                false,
+            ),
+            ("tag_quote", &tag_quotes, self.tag_quote_tree()?, true),
+            ("hash_quote", &hash_quotes, self.hash_quote_tree()?, true),
+        ];
+        if self.has_word_index()? {
+            linkage_trees.push(("word_quote", &word_quotes, self.word_quote_tree()?, true));
+        }
+        for (name, expected, tree, has_indices) in linkage_trees {
+            let stored = read_linkage_tree(&tree)?;
+            let format_value = |value: &str| {
+                if has_indices {
+                    format!("#{}", value)
+                } else {
+                    format!("{:?}", value)
+                }
+            };
+            for (key, values) in &stored {
+                for value in values {
+                    if has_indices && !indices.contains(value) {
+                        problems.push(format!(
+                            "{}: {:?} points to quote #{}, which doesn't exist",
+                            name, key, value
+                        ));
+                    } else if !expected.get(key).is_some_and(|e| e.contains(value)) {
+                        problems.push(format!(
+                            "{}: {:?} points to {}, but no quote links them",
+                            name,
+                            key,
+                            format_value(value)
+                        ));
+                    }
+                }
+            }
+            for (key, values) in expected {
+                let stored_values = stored.get(key);
+                // book_author only keeps the authors of one quote for a title shared by several authors
+                if name == "book_author" && stored_values.is_some() {
+                    continue;
+                }
+                for value in values {
+                    if !stored_values.is_some_and(|s| s.contains(value)) {
+                        problems.push(format!(
+                            "{}: {:?} is missing {}",
+                            name,
+                            key,
+                            format_value(value)
+                        ));
+                    }
+                }
+            }
+        }
+        problems.sort();
+        Ok(problems)
+    }
+
+    /// Rebuild the author, book, tag, word, and hash trees from the quotes in `quote_tree`, returns the number of quotes
+    pub fn reindex(&mut self) -> Result<usize, Error> {
+        for tree in &[
+            self.author_quote_tree()?,
+            self.author_book_tree()?,
+            self.book_quote_tree()?,
+            self.book_author_tree()?,
+            self.tag_quote_tree()?,
+            self.word_quote_tree()?,
+            self.hash_quote_tree()?,
+        ] {
+            tree.clear()?;
+        }
+        let mut quotes =
+            self.list_quotes_in_date_range(utils::date_start(None), utils::date_end(None))?;
+        quotes.sort_by_key(|quote| quote.index);
+        let links = QuoteLinks::new(&quotes);
+        self.transaction(|transaction| transaction.add_links(&links))?;
+        self.db.insert("word_index", "")?;
+        self.db.insert("hash_index", "")?;
+        Ok(quotes.len())
+    }
+
+    /// Renumber quotes from 1 without gaps (rewriting all trees), returns the number of quotes
+    pub fn renumber(&mut self) -> Result<usize, Error> {
+        let mut quotes =
+            self.list_quotes_in_date_range(utils::date_start(None), utils::date_end(None))?;
+        quotes.sort_by_key(|quote| quote.index);
+        let quote_tree = self.quote_tree()?;
+        let mut batch = sled::Batch::default();
+        for item in quote_tree.iter() {
+            let (index_key, _) = item.map_err(|_| QuothError::OutOfCheeseError {
+                message: "sled PageCache Error".into(),
+            })?;
+            batch.remove(index_key);
+        }
+        let num_quotes = quotes.len();
+        let new_indices: HashMap<usize, usize> = quotes
+            .iter()
+            .enumerate()
+            .map(|(i, quote)| (quote.index, i + 1))
+            .collect();
+        for (i, mut quote) in quotes.into_iter().enumerate() {
+            quote.index = i + 1;
+            quote.related = quote
+                .related
+                .iter()
+                .filter_map(|index| new_indices.get(index).copied())
+                .collect();
+            batch.insert(quote.index.to_string().as_bytes(), quote.to_bytes()?);
+        }
+        quote_tree.apply_batch(batch)?;
+        self.remap_collections(|index| new_indices.get(&index).copied())?;
+        self.db
+            .insert("quote_index", num_quotes.to_string().as_bytes())?;
+        self.reindex()?;
+        Ok(num_quotes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_trees() -> (tempfile::TempDir, Trees) {
+        let dir = tempfile::tempdir().unwrap();
+        let trees = Trees::read(&PathDir::new(dir.path()).unwrap()).unwrap();
+        (dir, trees)
+    }
+
+    fn new_quote(index: usize, book: &str, quote: &str, tags: &[&str]) -> Quote {
+        let date = Utc::now();
+        Quote {
+            index,
+            book: book.into(),
+            page: None,
+            authors: vec!["Ursula K. Le Guin".into()],
+            tags: tags.iter().map(|tag| (*tag).to_owned()).collect(),
+            date,
+            quote: quote.into(),
+            note: None,
+            rating: None,
+            source: None,
+            last_reviewed: None,
+            reviews: 0,
+            related: Vec::new(),
+            created: date,
+            modified: date,
+        }
+    }
+
+    /// Stores a quote without linking it in the other trees
+    fn store_unlinked(trees: &Trees, quote: &Quote) {
+        trees
+            .transaction(|transaction| transaction.store_quotes(slice::from_ref(quote)))
+            .unwrap();
+    }
+
+    fn copy_dir(from: &Path, to: &Path) {
+        fs::create_dir_all(to).unwrap();
+        for entry in fs::read_dir(from).unwrap() {
+            let path = entry.unwrap().path();
+            let new_path = to.join(path.file_name().unwrap());
+            if path.is_dir() {
+                copy_dir(&path, &new_path);
+            } else {
+                fs::copy(&path, &new_path).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn tags_are_found_whatever_their_case() {
+        let (_dir, mut trees) = new_trees();
+        let quote = new_quote(1, "The Dispossessed", "Q", &["Fiction", "SCI  fi"]);
+        trees.add_quote(&quote).unwrap();
+        assert_eq!(trees.get_tag_quotes("fiction").unwrap(), vec![1]);
+        assert_eq!(trees.get_tag_quotes("FICTION").unwrap(), vec![1]);
+        assert_eq!(trees.get_tag_quotes(" Sci Fi ").unwrap(), vec![1]);
+        assert_eq!(trees.get_tags().unwrap(), vec!["fiction", "sci fi"]);
+        assert_eq!(trees.get_quote(1).unwrap().tags, vec!["fiction", "sci fi"]);
+    }
+
+    #[test]
+    fn tags_are_found_whichever_way_lowercase_tags_was_set() {
+        let (_dir, mut trees) = new_trees();
+        trees.lowercase_tags = false;
+        trees
+            .add_quote(&new_quote(1, "The Dispossessed", "Q", &["Science Fiction"]))
+            .unwrap();
+        trees.lowercase_tags = true;
+        trees
+            .add_quote(&new_quote(2, "The Dispossessed", "R", &["SCIENCE fiction"]))
+            .unwrap();
+        assert_eq!(trees.get_quote(1).unwrap().tags, vec!["Science Fiction"]);
+        assert_eq!(trees.get_quote(2).unwrap().tags, vec!["science fiction"]);
+        for tag in &["science fiction", "Science Fiction", "SCIENCE FICTION"] {
+            assert_eq!(trees.get_tag_quotes(tag).unwrap(), vec![1, 2]);
+            assert!(trees.get_quote(1).unwrap().has_tag(tag));
+        }
+
+        trees.lowercase_tags = false;
+        assert_eq!(trees.rename_tag("science fiction", "SF").unwrap(), 2);
+        assert_eq!(trees.get_tag_quotes("sf").unwrap(), vec![1, 2]);
+        assert_eq!(trees.get_quote(2).unwrap().tags, vec!["SF"]);
+        assert!(trees.get_tag_quotes("science fiction").is_err());
+        assert!(trees.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn upgrade_lowercases_tag_keys() {
+        let (_dir, mut trees) = new_trees();
+        trees.lowercase_tags = false;
+        trees
+            .add_quote(&new_quote(1, "The Dispossessed", "Q", &["Fiction"]))
+            .unwrap();
+        // A key kept in its case by an earlier version
+        let tag_quote_tree = trees.tag_quote_tree().unwrap();
+        tag_quote_tree.remove("fiction").unwrap();
+        tag_quote_tree.insert("Fiction", "1").unwrap();
+
+        trees.upgrade().unwrap();
+        assert_eq!(trees.get_tags().unwrap(), vec!["fiction"]);
+        assert_eq!(trees.get_tag_quotes("FICTION").unwrap(), vec![1]);
+        assert_eq!(trees.get_quote(1).unwrap().tags, vec!["Fiction"]);
+    }
+
+    #[test]
+    fn stats_of_an_empty_database_is_an_error() {
+        let (_dir, trees) = new_trees();
+        let error = trees
+            .get_stats(utils::date_start(None), utils::date_end(None))
+            .unwrap_err();
+        match error.downcast_ref::<QuothError>() {
+            Some(QuothError::NoQuotesFound) => (),
+            _ => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn stats_of_one_quote_cover_one_month() {
+        let (_dir, mut trees) = new_trees();
+        trees
+            .add_quote(&new_quote(1, "The Dispossessed", "You shall be free", &[]))
+            .unwrap();
+        let stats = trees
+            .get_stats(utils::date_start(None), utils::date_end(None))
+            .unwrap();
+        assert_eq!(stats.months.len(), 1);
+        assert_eq!((stats.months[0].quotes, stats.months[0].books), (1, 1));
+        assert_eq!(stats.authors.len(), 1);
+        assert_eq!(
+            (stats.totals.quotes, stats.totals.books, stats.totals.words),
+            (1, 1, 4)
+        );
+    }
+
+    #[test]
+    fn interrupted_adds_deletes_and_changes_leave_every_tree_as_it_was() {
+        let (_dir, mut trees) = new_trees();
+        trees
+            .add_quote(&new_quote(1, "The Dispossessed", "Q", &["fiction"]))
+            .unwrap();
+        trees
+            .add_quote(&new_quote(2, "The Lathe Of Heaven", "Dreams", &["dreams"]))
+            .unwrap();
+        trees.link_quotes(1, 2).unwrap();
+        trees.add_to_collection("favourites", &[1, 2]).unwrap();
+        let added = new_quote(3, "Always Coming Home", "R", &["fiction"]);
+        let crash = || -> Result<(), Error> {
+            Err(QuothError::OutOfCheeseError {
+                message: "quoth died".into(),
+            }
+            .into())
+        };
+        // An add, a delete, and a change that stop right after their first write
+        assert!(trees
+            .transaction(|transaction| {
+                transaction.store_quotes(slice::from_ref(&added))?;
+                crash()
+            })
+            .is_err());
+        assert!(trees
+            .transaction(|transaction| {
+                transaction.remove_quote(1)?;
+                crash()
+            })
+            .is_err());
+        assert!(trees
+            .transaction(|transaction| {
+                transaction.remove_links(&transaction.get_quote(1)?)?;
+                crash()
+            })
+            .is_err());
+        assert!(trees.check().unwrap().is_empty());
+        assert_eq!(trees.get_quote_index().unwrap(), 2);
+        assert!(trees.get_quote(3).is_err());
+        assert_eq!(trees.get_tag_quotes("fiction").unwrap(), vec![1]);
+        assert_eq!(trees.get_quote(2).unwrap().related, vec![1]);
+        assert_eq!(
+            trees.get_collection_quotes("favourites").unwrap(),
+            vec![1, 2]
+        );
+
+        trees.delete_quote(1).unwrap();
+        assert!(trees.check().unwrap().is_empty());
+        assert!(trees.get_quote(2).unwrap().related.is_empty());
+        assert_eq!(trees.get_collection_quotes("favourites").unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn databases_from_sled_0_28_are_upgraded() {
+        // Written elsewhere and copied over, since sled holds its lock for a moment after a database is dropped
+        let old_dir = tempfile::tempdir().unwrap();
+        {
+            let old_db = old_sled::Db::open(old_dir.path()).unwrap();
+            old_db
+                .open_tree("quote")
+                .unwrap()
+                .insert(
+                    "1",
+                    new_quote(1, "The Dispossessed", "Q", &[])
+                        .to_bytes()
+                        .unwrap(),
+                )
+                .unwrap();
+            old_db.insert("quote_index", "1").unwrap();
+            old_db.flush().unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        copy_dir(old_dir.path(), &dir.path().join(config::DB_PATH));
+
+        let trees = Trees::read(&PathDir::new(dir.path()).unwrap()).unwrap();
+        assert_eq!(trees.get_quote(1).unwrap().book, "The Dispossessed");
+        assert_eq!(trees.get_quote_index().unwrap(), 1);
+        assert_eq!(trees.get_book_quotes("The Dispossessed").unwrap(), vec![1]);
+        assert!(trees.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn relocate_keeps_the_old_trees_if_the_counts_disagree() {
+        let (old_dir, mut trees) = new_trees();
+        trees
+            .add_quote(&new_quote(1, "The Dispossessed", "Q", &[]))
+            .unwrap();
+        // A quote left over in the new location makes the counts disagree
+        // (copied from elsewhere, since sled holds its lock for a moment after a database is dropped)
+        let (stray_dir, stray_trees) = new_trees();
+        store_unlinked(&stray_trees, &new_quote(5, "The Lathe Of Heaven", "Q", &[]));
+        stray_trees.db.flush().unwrap();
+        let new_dir = tempfile::tempdir().unwrap();
+        copy_dir(
+            &stray_dir.path().join(config::DB_PATH),
+            &new_dir.path().join(config::DB_PATH),
+        );
+        let new_quoth_dir = PathDir::new(new_dir.path()).unwrap();
+
+        let error = trees
+            .relocate(&PathDir::new(old_dir.path()).unwrap(), &new_quoth_dir)
+            .unwrap_err();
+        match error.downcast_ref::<QuothError>() {
+            Some(QuothError::RelocationError {
+                num_quotes: 1,
+                num_relocated: 2,
+            }) => (),
+            _ => panic!("unexpected error {:?}", error),
+        }
+        assert!(!new_dir.path().join(config::DB_PATH).exists());
+        assert_eq!(trees.get_quote(1).unwrap().book, "The Dispossessed");
+    }
+
+    #[test]
+    fn semicolons_in_titles_survive_the_separator_migration() {
+        let (_dir, mut trees) = new_trees();
+        let (author, book) = ("Ursula K. Le Guin", "The Earthsea Quartet; Tehanu");
+        store_unlinked(&trees, &new_quote(1, book, "Q", &[]));
+        store_unlinked(&trees, &new_quote(2, book, "R", &[]));
+        // Linkage trees as written when values were separated by semicolons
+        for (tree, key, value) in &[
+            (trees.author_quote_tree().unwrap(), author, "1;2"),
+            (trees.author_book_tree().unwrap(), author, book),
+            (trees.book_quote_tree().unwrap(), book, "1;2"),
+            (trees.book_author_tree().unwrap(), book, author),
+        ] {
+            tree.insert(key, *value).unwrap();
+        }
+        trees.db.remove("separator").unwrap();
+        assert!(!trees.check().unwrap().is_empty());
+
+        trees.upgrade().unwrap();
+        assert!(trees.check().unwrap().is_empty());
+        assert_eq!(trees.get_book_quotes(book).unwrap(), vec![1, 2]);
+        assert_eq!(trees.get_author_quotes(author).unwrap(), vec![1, 2]);
+        let author_books = read_linkage_tree(&trees.author_book_tree().unwrap()).unwrap();
+        assert_eq!(
+            author_books[author],
+            vec![book.to_owned()].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn untagged_quotes_leave_the_tag_tree_alone() {
+        let (_dir, mut trees) = new_trees();
+        assert!(utils::split_tags(" , ").is_empty());
+        trees
+            .add_quote(&new_quote(1, "The Dispossessed", "Q", &[]))
+            .unwrap();
+        trees
+            .add_quotes(&[new_quote(2, "The Dispossessed", "R", &["", " "])])
+            .unwrap();
+        assert!(trees.tag_quote_tree().unwrap().is_empty());
+        assert!(trees.get_tags().unwrap().is_empty());
+        trees.delete_quote(1).unwrap();
+        trees.delete_quote(2).unwrap();
+        assert!(trees.check().unwrap().is_empty());
+    }
 }
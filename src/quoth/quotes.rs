@@ -1,28 +1,144 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
 use anyhow::Error;
 use bincode;
-use chrono::{DateTime, Utc};
-use console::{Alignment, pad_str, style};
+use chrono::{Datelike, DateTime, Duration, Utc};
+use console::{Alignment, style};
+use csv;
 use path_abs::{FileRead, PathFile};
+use rayon::prelude::*;
+use seahash;
+use serde::{de, Deserialize, Deserializer};
 use serde_json;
-use textwrap::{termwidth, Wrapper};
+use serde_json::value::RawValue;
+use textwrap::{self, Wrapper};
+use unicode_width::UnicodeWidthStr;
 
+use crate::config;
+use crate::errors::QuothError;
+use crate::quoth::theme::Theme;
 use crate::utils;
 
 /// Stores information about a quote
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Quote {
     /// Quote index, used to retrieve and modify a quote
     pub index: usize,
     /// Title of the quote's book
     pub book: String,
-    /// Name of the quote's author
-    pub author: String,
+    /// Page of the book the quote is on
+    #[serde(default)]
+    pub page: Option<u32>,
+    /// Names of the quote's authors (stored as a single `author` by earlier versions)
+    #[serde(alias = "author", deserialize_with = "deserialize_authors")]
+    pub authors: Vec<String>,
     /// Tags attached to a quote
     pub tags: Vec<String>,
     /// Date of recording the quote
     pub date: DateTime<Utc>,
     /// Quote text
     pub quote: String,
+    /// Free-form commentary on the quote
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Rating from 1 to 5
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// URL the quote was found at
+    #[serde(default)]
+    pub source: Option<String>,
+    /// When the quote was last marked as reviewed by `quoth review`
+    #[serde(default)]
+    pub last_reviewed: Option<DateTime<Utc>>,
+    /// Number of times the quote was marked as reviewed (picks the interval until its next review)
+    #[serde(default)]
+    pub reviews: u32,
+    /// Indices of quotes linked to this one with `quoth link`
+    #[serde(default)]
+    pub related: Vec<usize>,
+    /// When the quote was added to quoth (unlike `date`, this can't be changed)
+    #[serde(default = "unrecorded_time")]
+    pub created: DateTime<Utc>,
+    /// When the quote was last changed
+    #[serde(default = "unrecorded_time")]
+    pub modified: DateTime<Utc>,
+}
+
+/// Reads a list of authors, or the single author string of earlier versions
+fn deserialize_authors<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Authors {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match Authors::deserialize(deserializer)? {
+        Authors::One(author) => vec![author],
+        Authors::Many(authors) => authors,
+    })
+}
+
+/// Stands in for the `created` and `modified` times of quotes stored before quoth recorded them,
+/// until `Quote::fill_timestamps` replaces it
+fn unrecorded_time() -> DateTime<Utc> {
+    utils::date_start(None)
+}
+
+/// Quote layout stored with `bincode` by earlier versions of quoth.
+/// `bincode` can't skip missing fields, so quotes are now stored as JSON and new fields use `#[serde(default)]`
+#[derive(Deserialize, Debug)]
+struct LegacyQuote {
+    index: usize,
+    book: String,
+    author: String,
+    tags: Vec<String>,
+    date: DateTime<Utc>,
+    quote: String,
+}
+
+impl From<LegacyQuote> for Quote {
+    fn from(quote: LegacyQuote) -> Self {
+        Quote {
+            index: quote.index,
+            book: quote.book,
+            page: None,
+            authors: vec![quote.author],
+            tags: quote.tags,
+            date: quote.date,
+            quote: quote.quote,
+            note: None,
+            rating: None,
+            source: None,
+            last_reviewed: None,
+            reviews: 0,
+            related: Vec::new(),
+            created: quote.date,
+            modified: quote.date,
+        }
+    }
+}
+
+/// How quotes are printed by `Quote::to_pretty` and `Quote::to_boxed`
+pub struct PrintStyle {
+    /// Width to lay quotes out for (narrower widths are treated as `config::MIN_TERMINAL_WIDTH`)
+    pub width: usize,
+    /// Shown above each quote by `to_pretty`
+    pub symbol: String,
+    pub theme: Theme,
+}
+
+impl Default for PrintStyle {
+    /// As wide as the terminal, with a raven above each quote and the default theme
+    fn default() -> Self {
+        PrintStyle {
+            width: utils::terminal_width(),
+            symbol: utils::RAVEN.to_string(),
+            theme: Theme::default(),
+        }
+    }
 }
 
 /// Stores quote information as Strings for writing to a file
@@ -32,7 +148,9 @@ pub struct TSVQuote {
     index: usize,
     /// Title of the quote's book
     book: String,
-    /// Name of the quote's author
+    /// Page of the book the quote is on (empty if not known)
+    page: String,
+    /// Names of the quote's authors (comma separated)
     author: String,
     /// Tags attached to a quote
     tags: String,
@@ -40,38 +158,78 @@ pub struct TSVQuote {
     date: String,
     /// Quote text
     quote: String,
+    /// Free-form commentary on the quote (empty if none)
+    note: String,
+    /// Rating from 1 to 5 (empty if not rated)
+    rating: String,
+    /// URL the quote was found at (empty if none)
+    source: String,
 }
 impl From<Quote> for TSVQuote {
     fn from(quote: Quote) -> Self {
+        TSVQuote::from_quote(quote, "%Y-%m-%d")
+    }
+}
+
+impl TSVQuote {
+    /// Formats a quote as a single tab-separated row, in the column order of `quoth export`
+    pub fn to_row(self) -> Result<String, Error> {
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_writer(Vec::new());
+        writer.serialize(self)?;
+        let row = writer
+            .into_inner()
+            .map_err(|_| QuothError::OutOfCheeseError {
+                message: "Couldn't write TSV row".into(),
+            })?;
+        Ok(utils::u8_to_str(&row)?
+            .trim_end_matches(['\r', '\n'])
+            .to_owned())
+    }
+
+    /// Converts a quote, writing its date with a given chrono format string
+    /// (check it first with `utils::check_date_format`)
+    pub fn from_quote(quote: Quote, date_format: &str) -> Self {
         TSVQuote {
             index: quote.index,
             book: quote.book,
-            author: quote.author,
+            page: quote.page.map(|page| page.to_string()).unwrap_or_default(),
+            author: quote.authors.join(","),
             tags: quote.tags.join(","),
-            date: quote.date.date().format("%Y-%m-%d").to_string(),
+            date: quote.date.date().format(date_format).to_string(),
             quote: quote.quote,
+            note: quote.note.unwrap_or_default(),
+            rating: quote
+                .rating
+                .map(|rating| rating.to_string())
+                .unwrap_or_default(),
+            source: quote.source.unwrap_or_default(),
         }
     }
 }
 
+/// Quote text, author, book, tags, and note on separate lines (used for searching)
 impl ToString for Quote {
     fn to_string(&self) -> String {
         format!(
-            "{}\n{}\n{}\n{}",
+            "{}\n{}\n{}\n{}\n{}",
             self.quote,
-            self.author,
+            self.author_names(),
             self.book,
-            self.tags.join(",")
+            self.tags.join(","),
+            self.note.as_deref().unwrap_or("")
         )
     }
 }
 
 impl Quote {
-    /// New quote
+    /// New quote (`authors` and `tags` are comma separated)
     pub fn new(
         index: usize,
         title: &str,
-        author: &str,
+        authors: &str,
         tags: &str,
         date: DateTime<Utc>,
         quote: String,
@@ -79,32 +237,101 @@ impl Quote {
         Quote {
             index,
             book: utils::camel_case_phrase(title),
-            author: utils::camel_case_phrase(author),
+            page: None,
+            authors: utils::split_authors(authors),
             tags: utils::split_tags(tags),
             date,
             quote,
+            note: None,
+            rating: None,
+            source: None,
+            last_reviewed: None,
+            reviews: 0,
+            related: Vec::new(),
+            created: Utc::now(),
+            modified: Utc::now(),
         }
     }
 
-    pub fn from_user(index: usize, default_quote: Option<Quote>) -> Result<Quote, Error> {
+    /// Prompts for a quote (defaulting to `default_quote`), completing authors from `known_authors` (and offering one
+    /// of them for each author typed in that is a near miss) and tags from `known_tags`
+    pub fn from_user(
+        index: usize,
+        default_quote: Option<Quote>,
+        known_authors: &[String],
+        known_tags: &[String],
+    ) -> Result<Quote, Error> {
+        let default_note = default_quote.as_ref().and_then(|q| q.note.clone());
+        // Editing a quote keeps its review history and links
+        let (last_reviewed, reviews, related) =
+            default_quote.as_ref().map_or((None, 0, Vec::new()), |q| {
+                (q.last_reviewed, q.reviews, q.related.clone())
+            });
         let default_quote = match default_quote {
             Some(q) => Some(TSVQuote::from(q)),
             None => None,
         };
-        let (default_title, default_author, default_tags, default_date, default_text) =
-            match default_quote {
-                Some(q) => (
-                    Some(q.book),
-                    Some(q.author),
-                    Some(q.tags),
-                    Some(q.date),
-                    Some(q.quote),
-                ),
-                None => (None, None, None, None, None),
-            };
+        let (
+            default_title,
+            default_page,
+            default_author,
+            default_tags,
+            default_date,
+            default_text,
+            default_rating,
+            default_source,
+        ) = match default_quote {
+            Some(q) => (
+                Some(q.book),
+                Some(q.page),
+                Some(q.author),
+                Some(q.tags),
+                Some(q.date),
+                Some(q.quote),
+                Some(q.rating),
+                Some(q.source),
+            ),
+            None => (None, None, None, None, None, None, None, None),
+        };
         let title = utils::user_input("Book Title", default_title.as_deref(), false)?;
-        let author = utils::user_input("Author", default_author.as_deref(), false)?;
-        let tags = utils::user_input("Tags (comma separated)", default_tags.as_deref(), false)?;
+        let page = utils::parse_page(&utils::user_input(
+            "Page (optional)",
+            Some(default_page.as_deref().unwrap_or("")),
+            false,
+        )?)?;
+        let mut authors = utils::split_authors(&utils::completed_user_input(
+            "Authors (comma separated)",
+            default_author.as_deref(),
+            false,
+            Some(&utils::Completer {
+                values: known_authors,
+                separator: Some(','),
+            }),
+        )?);
+        for author in &mut authors {
+            if let Some(existing_author) =
+                utils::closest_match(author, known_authors, config::AUTHOR_MATCH_THRESHOLD)
+            {
+                let use_existing = utils::user_input(
+                    &format!("Did you mean {}? Y/N", existing_author),
+                    Some("Y"),
+                    true,
+                )?
+                .to_ascii_uppercase();
+                if use_existing == "Y" {
+                    *author = existing_author.to_owned();
+                }
+            }
+        }
+        let tags = utils::completed_user_input(
+            "Tags (comma separated)",
+            default_tags.as_deref(),
+            false,
+            Some(&utils::Completer {
+                values: known_tags,
+                separator: Some(','),
+            }),
+        )?;
         let date = match default_date {
             Some(_) => {
                 utils::parse_date(&utils::user_input("Date", default_date.as_deref(), true)?)?
@@ -120,22 +347,385 @@ impl Quote {
         if quote_text.is_empty() {
             quote_text = utils::external_editor_input(default_text.as_deref())?;
         }
-        Ok(Quote::new(index, &title, &author, &tags, date, quote_text))
+        let rating = utils::parse_rating(&utils::user_input(
+            "Rating 1-5 (optional)",
+            Some(default_rating.as_deref().unwrap_or("")),
+            false,
+        )?)?;
+        let source = utils::parse_source(&utils::user_input(
+            "Source URL (optional)",
+            Some(default_source.as_deref().unwrap_or("")),
+            false,
+        )?)?;
+        let edit_note = utils::user_input("Edit note in external editor Y/N?", Some("N"), true)?
+            .to_ascii_uppercase();
+        let note = if edit_note == "Y" {
+            let note = utils::external_editor_input(default_note.as_deref())?;
+            if note.trim().is_empty() {
+                None
+            } else {
+                Some(note.trim().to_owned())
+            }
+        } else {
+            default_note
+        };
+        Ok(Quote {
+            page,
+            note,
+            rating,
+            source,
+            last_reviewed,
+            reviews,
+            related,
+            ..Quote::new(index, &title, &authors.join(","), &tags, date, quote_text)
+        })
+    }
+
+    /// Changes a single field of a quote, parsing the value like `from_user` would
+    pub fn with_field(mut self, field: &str, value: &str) -> Result<Quote, Error> {
+        let value = value.trim();
+        let required = |value: &str| -> Result<String, Error> {
+            if value.is_empty() {
+                Err(QuothError::NoInputError.into())
+            } else {
+                Ok(value.to_owned())
+            }
+        };
+        match field.to_ascii_lowercase().as_str() {
+            "book" => self.book = utils::camel_case_phrase(&required(value)?),
+            "author" | "authors" => {
+                self.authors = utils::split_authors(value);
+                if self.authors.is_empty() {
+                    return Err(QuothError::NoInputError.into());
+                }
+            }
+            "tags" => self.tags = utils::split_tags(value),
+            "date" => self.date = utils::parse_date(&required(value)?)?.and_hms(0, 0, 0),
+            "quote" => self.quote = required(value)?,
+            "page" => self.page = utils::parse_page(value)?,
+            "note" => self.note = required(value).ok(),
+            "rating" => self.rating = utils::parse_rating(value)?,
+            "source" => self.source = utils::parse_source(value)?,
+            _ => {
+                return Err(QuothError::UnknownField {
+                    field: field.to_owned(),
+                }
+                .into())
+            }
+        }
+        Ok(self)
     }
 
     pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
-        Ok(bincode::serialize(&self)?)
+        Ok(serde_json::to_vec(&self)?)
     }
 
+    /// Reads a stored quote, falling back to the `bincode` layout of earlier versions
+    /// (earlier versions also stored an empty tag for quotes without tags, which is dropped)
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
-        Ok(bincode::deserialize(bytes)?)
+        let mut quote: Quote = match serde_json::from_slice(bytes) {
+            Ok(quote) => quote,
+            Err(_) => Quote::from(bincode::deserialize::<LegacyQuote>(bytes)?),
+        };
+        quote.tags.retain(|tag| !tag.is_empty());
+        Ok(quote.fill_timestamps())
+    }
+
+    /// Gives quotes stored or exported before quoth recorded creation and modification times the quote's date for both
+    fn fill_timestamps(mut self) -> Self {
+        if self.created == unrecorded_time() {
+            self.created = self.date;
+        }
+        if self.modified == unrecorded_time() {
+            self.modified = self.created;
+        }
+        self
+    }
+
+    /// Parses a quote from a JSON file (a rating outside 1 to 5 is an error, like in TSV and CSV files)
+    fn from_json(json: &RawValue) -> serde_json::Result<Self> {
+        let quote: Quote = serde_json::from_str(json.get())?;
+        if let Some(rating) = quote.rating {
+            if !(1..=5).contains(&rating) {
+                return Err(de::Error::custom(QuothError::BadRating {
+                    rating: rating.to_string(),
+                }));
+            }
+        }
+        Ok(quote.fill_timestamps())
     }
 
     /// Read quotes from a JSON file and return consumable iterator
+    /// The file can have a stream of quote objects (as written by `write_json`) or a single array of them
     pub fn read_from_file(
         json_file: &PathFile,
     ) -> Result<impl Iterator<Item = serde_json::Result<Quote>>, Error> {
-        Ok(serde_json::Deserializer::from_reader(FileRead::open(json_file)?).into_iter::<Self>())
+        Ok(Quote::read_json_records(json_file)?
+            .map(|record| record.and_then(|record| Quote::from_json(&record))))
+    }
+
+    /// Like `read_from_file`, but parses the quotes on all CPU cores (the quotes are in the same order as in the file)
+    pub fn read_from_file_parallel(
+        json_file: &PathFile,
+    ) -> Result<Vec<serde_json::Result<Quote>>, Error> {
+        let records: Vec<_> = Quote::read_json_records(json_file)?.collect();
+        Ok(records
+            .into_par_iter()
+            .map(|record| record.and_then(|record| Quote::from_json(&record)))
+            .collect())
+    }
+
+    /// Splits a JSON file (a stream of objects or a single array of them) into the unparsed JSON of each quote,
+    /// so a quote with missing or wrong fields doesn't stop the rest being read
+    fn read_json_records(
+        json_file: &PathFile,
+    ) -> Result<Box<dyn Iterator<Item = serde_json::Result<Box<RawValue>>>>, Error> {
+        let mut reader = io::BufReader::new(FileRead::open(json_file)?);
+        // Skips whitespace to see whether the file starts with an array
+        let starts_with_array = loop {
+            let buffer = reader.fill_buf()?;
+            match buffer.iter().position(|byte| !byte.is_ascii_whitespace()) {
+                Some(position) => {
+                    let first_byte = buffer[position];
+                    reader.consume(position);
+                    break first_byte == b'[';
+                }
+                None if buffer.is_empty() => break false,
+                None => {
+                    let length = buffer.len();
+                    reader.consume(length);
+                }
+            }
+        };
+        if starts_with_array {
+            let records: Vec<Box<RawValue>> = serde_json::from_reader(reader)?;
+            Ok(Box::new(records.into_iter().map(Ok)))
+        } else {
+            Ok(Box::new(
+                serde_json::Deserializer::from_reader(reader).into_iter::<Box<RawValue>>(),
+            ))
+        }
+    }
+
+    /// Write quotes as a stream of JSON objects (readable by `read_from_file`)
+    pub fn write_json<W: Write>(
+        quotes: impl IntoIterator<Item = Result<Quote, Error>>,
+        writer: W,
+    ) -> Result<(), Error> {
+        let mut writer = io::BufWriter::new(writer);
+        for quote in quotes {
+            serde_json::to_writer(&mut writer, &quote?)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes quotes as an iCalendar file with an all-day event for each book, on the date of its first quote,
+    /// with "Book — Author" as the summary and the book's quotes (oldest first) as the description
+    pub fn write_ical<W: Write>(
+        quotes: impl IntoIterator<Item = Result<Quote, Error>>,
+        writer: W,
+    ) -> Result<(), Error> {
+        let mut quotes = quotes.into_iter().collect::<Result<Vec<_>, _>>()?;
+        quotes.sort_by_key(|quote| (quote.date, quote.index));
+        // Books in the order they were first quoted
+        let mut books: Vec<Vec<Quote>> = Vec::new();
+        let mut book_positions = HashMap::new();
+        for quote in quotes {
+            let key = (quote.book.clone(), quote.authors.clone());
+            let position = *book_positions.entry(key).or_insert_with(|| {
+                books.push(Vec::new());
+                books.len() - 1
+            });
+            books[position].push(quote);
+        }
+        let mut writer = io::BufWriter::new(writer);
+        let timestamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        writer.write_all(b"BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//quoth//quoth//EN\r\n")?;
+        for book_quotes in books {
+            let first = &book_quotes[0];
+            let description = book_quotes
+                .iter()
+                .map(|quote| quote.quote.trim())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            for line in &[
+                "BEGIN:VEVENT".to_owned(),
+                format!("UID:quoth-{}@quoth", first.index),
+                format!("DTSTAMP:{}", timestamp),
+                format!("DTSTART;VALUE=DATE:{}", first.date.format("%Y%m%d")),
+                format!(
+                    "DTEND;VALUE=DATE:{}",
+                    (first.date + Duration::days(1)).format("%Y%m%d")
+                ),
+                format!(
+                    "SUMMARY:{}",
+                    utils::escape_ical(&format!("{} — {}", first.book, first.author_names()))
+                ),
+                format!("DESCRIPTION:{}", utils::escape_ical(&description)),
+                "END:VEVENT".to_owned(),
+            ] {
+                writer.write_all(utils::fold_ical_line(line).as_bytes())?;
+            }
+        }
+        writer.write_all(b"END:VCALENDAR\r\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes quotes as an RSS feed titled `title`, newest first, with an item for each quote
+    /// (quote text as the description, "Book — Author" as the title, and tags as categories)
+    pub fn write_rss<W: Write>(
+        quotes: impl IntoIterator<Item = Result<Quote, Error>>,
+        writer: W,
+        title: &str,
+    ) -> Result<(), Error> {
+        let mut quotes = quotes.into_iter().collect::<Result<Vec<_>, _>>()?;
+        quotes.sort_by_key(|quote| (quote.date, quote.index));
+        quotes.reverse();
+        let mut writer = io::BufWriter::new(writer);
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<rss version="2.0">"#)?;
+        writeln!(writer, "<channel>")?;
+        writeln!(writer, "  <title>{}</title>", utils::escape_xml(title))?;
+        writeln!(
+            writer,
+            "  <description>{}</description>",
+            utils::escape_xml(title)
+        )?;
+        writeln!(
+            writer,
+            "  <lastBuildDate>{}</lastBuildDate>",
+            Utc::now().to_rfc2822()
+        )?;
+        for quote in quotes {
+            writeln!(writer, "  <item>")?;
+            writeln!(
+                writer,
+                "    <title>{}</title>",
+                utils::escape_xml(&format!("{} — {}", quote.book, quote.author_names()))
+            )?;
+            writeln!(
+                writer,
+                "    <description>{}</description>",
+                utils::escape_xml(quote.quote.trim())
+            )?;
+            writeln!(
+                writer,
+                "    <author>{}</author>",
+                utils::escape_xml(&quote.author_names())
+            )?;
+            if let Some(source) = &quote.source {
+                writeln!(writer, "    <link>{}</link>", utils::escape_xml(source))?;
+            }
+            for tag in &quote.tags {
+                writeln!(
+                    writer,
+                    "    <category>{}</category>",
+                    utils::escape_xml(tag)
+                )?;
+            }
+            writeln!(
+                writer,
+                r#"    <guid isPermaLink="false">quoth-{}</guid>"#,
+                quote.index
+            )?;
+            writeln!(writer, "    <pubDate>{}</pubDate>", quote.date.to_rfc2822())?;
+            writeln!(writer, "  </item>")?;
+        }
+        writeln!(writer, "</channel>")?;
+        writeln!(writer, "</rss>")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Authors (in any order), book, and normalized text: quotes that share these are duplicates
+    pub fn duplicate_key(&self) -> (Vec<String>, String, String) {
+        let mut authors = self.authors.clone();
+        authors.sort();
+        (
+            authors,
+            self.book.clone(),
+            utils::normalize_text(&self.quote),
+        )
+    }
+
+    /// Hash of `duplicate_key` (with `seahash`, so it stays the same across runs and platforms)
+    pub fn duplicate_hash(&self) -> u64 {
+        let (authors, book, text) = self.duplicate_key();
+        let separator = [utils::SEPARATOR];
+        let mut bytes = Vec::new();
+        for author in authors {
+            bytes.extend_from_slice(author.as_bytes());
+            bytes.extend_from_slice(&separator);
+        }
+        bytes.extend_from_slice(&separator);
+        bytes.extend_from_slice(book.as_bytes());
+        bytes.extend_from_slice(&separator);
+        bytes.extend_from_slice(text.as_bytes());
+        seahash::hash(&bytes)
+    }
+
+    /// Authors' names separated by commas, as shown with the quote
+    pub fn author_names(&self) -> String {
+        self.authors.join(", ")
+    }
+
+    /// "— Author, Book, p. N" line used by `to_plain` and `to_markdown`
+    fn attribution(&self, book: &str) -> String {
+        match self.page {
+            Some(page) => format!("— {}, {}, p. {}", self.author_names(), book, page),
+            None => format!("— {}, {}", self.author_names(), book),
+        }
+    }
+
+    /// Formats a quote as plain text: the quote followed by its author and book
+    pub fn to_plain(&self) -> String {
+        format!("{}\n{}", self.quote.trim(), self.attribution(&self.book))
+    }
+
+    /// Formats a quote as a Markdown block quote ending with its author and (italicized) book
+    pub fn to_markdown(&self) -> String {
+        let mut markdown: String = self
+            .quote
+            .trim()
+            .lines()
+            .map(|line| format!("> {}\n", line).replace("> \n", ">\n"))
+            .collect();
+        markdown.push_str(&format!(
+            ">\n> {}",
+            self.attribution(&format!("*{}*", self.book))
+        ));
+        markdown
+    }
+
+    /// Formats a quote as a BibTeX entry keyed on the first author's surname and quote index
+    pub fn to_bibtex(&self) -> String {
+        let surname: String = self
+            .authors
+            .first()
+            .map_or("", String::as_str)
+            .split_whitespace()
+            .last()
+            .unwrap_or("anonymous")
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        let pages = match self.page {
+            Some(page) => format!("  pages = {{{}}},\n", page),
+            None => String::new(),
+        };
+        format!(
+            "@book{{{}{},\n  author = {{{}}},\n  title = {{{}}},\n{}  note = {{{}}},\n  year = {{{}}}\n}}\n",
+            surname.to_lowercase(),
+            self.index,
+            utils::escape_tex(&self.authors.join(" and ")),
+            utils::escape_tex(&self.book),
+            pages,
+            utils::escape_tex(self.quote.trim()),
+            self.date.year()
+        )
     }
 
     /// Filters quotes in date range
@@ -150,67 +740,393 @@ impl Quote {
             .collect())
     }
 
-    /// Checks if a quote was recorded within a date range
+    /// Sorts quotes by index, author, book, date, or when they were added (quotes that tie keep their order)
+    pub fn sort_by_field(quotes: &mut [Quote], field: &str) -> Result<(), Error> {
+        match field {
+            "index" => quotes.sort_by_key(|quote| quote.index),
+            "author" => {
+                quotes.sort_by_cached_key(|quote| utils::camel_case_phrase(&quote.author_names()))
+            }
+            "book" => quotes.sort_by_cached_key(|quote| utils::camel_case_phrase(&quote.book)),
+            "date" => quotes.sort_by_key(|quote| quote.date),
+            "added" => quotes.sort_by_key(|quote| quote.created),
+            _ => {
+                return Err(QuothError::OutOfCheeseError {
+                    message: format!("Can't sort quotes by {:?}", field),
+                }
+                .into())
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if a quote was recorded within a date range (including both ends)
     pub fn in_date_range(&self, from_date: DateTime<Utc>, to_date: DateTime<Utc>) -> bool {
-        from_date <= self.date && self.date < to_date
+        from_date <= self.date && self.date <= to_date
+    }
+
+    /// Checks if a quote was added to quoth within a date range (including both ends)
+    pub fn added_in_date_range(&self, from_date: DateTime<Utc>, to_date: DateTime<Utc>) -> bool {
+        from_date <= self.created && self.created <= to_date
+    }
+
+    /// Checks if a quote is due for review: never reviewed, or last reviewed at least
+    /// `config::REVIEW_INTERVALS` days ago (the interval grows with the number of reviews)
+    pub fn is_due_for_review(&self, now: DateTime<Utc>) -> bool {
+        match self.last_reviewed {
+            None => true,
+            Some(last_reviewed) => {
+                let interval = config::REVIEW_INTERVALS
+                    [(self.reviews.max(1) as usize - 1).min(config::REVIEW_INTERVALS.len() - 1)];
+                last_reviewed + Duration::days(interval) <= now
+            }
+        }
     }
 
-    /// Check if a quote has a particular tag associated with it
+    /// Number of whitespace-separated words in the quote text
+    pub fn word_count(&self) -> usize {
+        self.quote.split_whitespace().count()
+    }
+
+    /// Check if a quote has a particular tag associated with it (whatever the case and spacing of either, see `utils::normalize_tag`)
     pub fn has_tag(&self, tag: &str) -> bool {
-        self.tags.contains(&tag.into())
+        let tag = utils::normalize_tag(tag);
+        self.tags
+            .iter()
+            .any(|quote_tag| utils::normalize_tag(quote_tag) == tag)
+    }
+
+    /// Formats a quote as `#<index> | <author> — <book>: <quote>` on a single line,
+    /// cutting the quote text short to fit in `width` columns
+    pub fn oneline(&self, width: usize) -> String {
+        let prefix = format!(
+            "#{} | {} — {}: ",
+            self.index,
+            self.author_names(),
+            self.book
+        );
+        let text = self.quote.split_whitespace().collect::<Vec<_>>().join(" ");
+        let text_width = width
+            .saturating_sub(UnicodeWidthStr::width(prefix.as_str()))
+            .max(config::MIN_TERMINAL_WIDTH / 2);
+        if UnicodeWidthStr::width(text.as_str()) <= text_width {
+            return prefix + &text;
+        }
+        match textwrap::wrap(&text, text_width - 1).first() {
+            Some(line) => format!("{}{}…", prefix, line),
+            None => prefix,
+        }
     }
 
     /// Display a quote in the terminal prettily
     pub fn pretty_print(&self) {
-        let width = termwidth() - 4;
+        self.pretty_print_with(&PrintStyle::default())
+    }
+
+    /// Display a quote prettily with the given print style
+    pub fn pretty_print_with(&self, print_style: &PrintStyle) {
+        print!("{}", self.to_pretty(print_style))
+    }
+
+    /// Display a quote inside a box (instead of `pretty_print`'s centered layout)
+    pub fn boxed_print(&self) {
+        print!("{}", self.to_boxed(&PrintStyle::default()))
+    }
+
+    /// The quote text (and note) followed by its right-aligned author and book, in a box just wide enough for them
+    /// (at most `print_style.width` columns)
+    pub fn to_boxed(&self, print_style: &PrintStyle) -> String {
+        // Each side has a border and a space
+        let wrapper = Wrapper::new(print_style.width.max(config::MIN_TERMINAL_WIDTH) - 4);
+        let wrap = |text: &str| -> Vec<String> {
+            text.split('\n')
+                .flat_map(|line| {
+                    let lines = wrapper.wrap(line);
+                    if lines.is_empty() {
+                        vec![String::new()]
+                    } else {
+                        lines.into_iter().map(|line| line.into_owned()).collect()
+                    }
+                })
+                .collect()
+        };
+        let text_lines = wrap(self.quote.trim());
+        let note_lines = self.note.as_ref().map(|note| wrap(note.trim()));
+        let author_lines = wrap(&format!("— {}", self.author_names()));
+        let book_lines = wrap(&match self.page {
+            Some(page) => format!("{}, p. {}", self.book, page),
+            None => self.book.clone(),
+        });
+        let label = format!(" #{} ", self.index);
+        let width = text_lines
+            .iter()
+            .chain(note_lines.iter().flatten())
+            .chain(author_lines.iter())
+            .chain(book_lines.iter())
+            .map(|line| line.width())
+            .chain(std::iter::once(label.width() - 1))
+            .max()
+            .unwrap_or(0);
+        let row = |line: &str, styled: String, alignment: Alignment| {
+            let padding = " ".repeat(width - line.width());
+            match alignment {
+                Alignment::Right => format!("│ {}{} │\n", padding, styled),
+                _ => format!("│ {}{} │\n", styled, padding),
+            }
+        };
+        let theme = &print_style.theme;
+        let mut boxed = format!(
+            "╭─{}{}╮\n",
+            style(&label).dim(),
+            "─".repeat(width + 1 - label.width())
+        );
+        for line in &text_lines {
+            boxed.push_str(&row(
+                line,
+                theme.quote.apply(line).to_string(),
+                Alignment::Left,
+            ));
+        }
+        if let Some(note_lines) = &note_lines {
+            boxed.push_str(&row("", String::new(), Alignment::Left));
+            for line in note_lines {
+                boxed.push_str(&row(line, style(line).dim().to_string(), Alignment::Left));
+            }
+        }
+        boxed.push_str(&row("", String::new(), Alignment::Left));
+        for line in &author_lines {
+            boxed.push_str(&row(
+                line,
+                theme.author.apply(line).to_string(),
+                Alignment::Right,
+            ));
+        }
+        for line in &book_lines {
+            boxed.push_str(&row(
+                line,
+                theme.book.apply(line).to_string(),
+                Alignment::Right,
+            ));
+        }
+        boxed.push_str(&format!("╰{}╯\n", "─".repeat(width + 2)));
+        boxed
+    }
+
+    /// The text `pretty_print_with` displays
+    pub fn to_pretty(&self, print_style: &PrintStyle) -> String {
+        let width = print_style.width.max(config::MIN_TERMINAL_WIDTH) - 4;
         let wrapper = Wrapper::new(width)
             .initial_indent("  ")
             .subsequent_indent("  ");
-        print!(
+        let theme = &print_style.theme;
+        let mut pretty = String::new();
+        pretty.push_str(&format!(
             "{}",
-            style(pad_str(
-                &utils::RAVEN.to_string(),
+            style(utils::pad_lines(
+                &print_style.symbol,
                 width,
-                Alignment::Center,
-                None
+                Alignment::Center
             ))
             .dim()
-        );
+        ));
         for line in self.quote.split('\n') {
-            println!(
-                "\n{}",
-                pad_str(&wrapper.fill(line), width, Alignment::Center, None)
-            );
+            pretty.push_str(&format!(
+                "\n{}\n",
+                theme.quote.apply(utils::pad_lines(
+                    &wrapper.fill(line),
+                    width,
+                    Alignment::Center
+                ))
+            ));
         }
-        println!(
-            "{}",
-            style(pad_str(
+        if let Some(note) = &self.note {
+            for line in note.split('\n') {
+                pretty.push_str(&format!(
+                    "{}\n",
+                    style(utils::pad_lines(
+                        &wrapper.fill(line),
+                        width,
+                        Alignment::Center
+                    ))
+                    .dim()
+                ));
+            }
+        }
+        pretty.push_str(&format!(
+            "{}\n",
+            style(utils::pad_lines(
                 &format!("--#{}--", self.index),
                 width,
-                Alignment::Center,
-                None
+                Alignment::Center
             ))
             .dim()
-        );
-        println!(
-            "{}",
-            style(pad_str(&self.author, width - 4, Alignment::Right, None)).blue()
-        );
-        println!(
-            "{}",
-            style(pad_str(&self.book, width - 4, Alignment::Right, None))
-                .cyan()
-                .italic()
-        );
-        println!(
+        ));
+        pretty.push_str(&format!(
             "{}\n",
-            style(pad_str(
+            theme.author.apply(utils::pad_lines(
+                &self.author_names(),
+                width - 4,
+                Alignment::Right
+            ))
+        ));
+        let book = match self.page {
+            Some(page) => format!("{}, p. {}", self.book, page),
+            None => self.book.clone(),
+        };
+        pretty.push_str(&format!(
+            "{}\n",
+            theme
+                .book
+                .apply(utils::pad_lines(&book, width - 4, Alignment::Right))
+        ));
+        if let Some(source) = &self.source {
+            pretty.push_str(&format!(
+                "{}\n",
+                style(utils::pad_lines(source, width - 4, Alignment::Right)).dim()
+            ));
+        }
+        if let Some(rating) = self.rating {
+            pretty.push_str(&format!(
+                "{}\n",
+                style(utils::pad_lines(
+                    &format!(
+                        "{}{}",
+                        "★".repeat(rating as usize),
+                        "☆".repeat(5usize.saturating_sub(rating as usize))
+                    ),
+                    width - 4,
+                    Alignment::Right
+                ))
+                .yellow()
+            ));
+        }
+        if !self.related.is_empty() {
+            let related: Vec<String> = self
+                .related
+                .iter()
+                .map(|index| format!("#{}", index))
+                .collect();
+            pretty.push_str(&format!(
+                "{}\n",
+                style(utils::pad_lines(
+                    &format!("related: {}", related.join(", ")),
+                    width - 4,
+                    Alignment::Right
+                ))
+                .dim()
+            ));
+        }
+        pretty.push_str(&format!(
+            "{}\n\n",
+            theme.tags.apply(utils::pad_lines(
                 &self.tags.join(", "),
                 width - 4,
-                Alignment::Right,
-                None
+                Alignment::Right
             ))
-            .dim()
-        )
+        ));
+        pretty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn json_file(contents: &str) -> (tempfile::NamedTempFile, PathFile) {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        let path = PathFile::new(file.path()).unwrap();
+        (file, path)
+    }
+
+    fn new_quote(text: &str) -> Quote {
+        let json = serde_json::json!({
+            "index": 1,
+            "book": "The Left Hand Of Darkness",
+            "authors": ["Ursula K. Le Guin"],
+            "tags": ["fiction"],
+            "date": "2019-11-01T12:00:00Z",
+            "quote": text,
+        });
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn json_ratings_outside_1_to_5_are_rejected() {
+        let (_file, path) = json_file(
+            r#"{"index":1,"book":"B","author":"A","tags":[],"date":"2019-11-01T00:00:00Z","quote":"Q","rating":7}
+{"index":2,"book":"B","author":"A","tags":[],"date":"2019-11-01T00:00:00Z","quote":"Q","rating":5}"#,
+        );
+        let quotes: Vec<_> = Quote::read_from_file(&path).unwrap().collect();
+        assert!(quotes[0].is_err());
+        assert_eq!(quotes[1].as_ref().unwrap().rating, Some(5));
+    }
+
+    #[test]
+    fn late_quotes_are_in_their_day() {
+        let mut quote = new_quote("Light is the left hand of darkness");
+        quote.date = "2019-11-01T23:59:59.5Z".parse().unwrap();
+        let day = Utc.ymd(2019, 11, 1);
+        assert!(quote.in_date_range(day.and_hms(0, 0, 0), utils::end_of_day(day)));
+        assert!(!quote.in_date_range(utils::date_start(None), utils::end_of_day(day.pred())));
+    }
+
+    #[test]
+    fn tiny_widths_dont_panic() {
+        let mut quote = new_quote("Lightisthelefthandofdarknessanddarknesstherighthandoflight");
+        quote.note = Some("A note".into());
+        quote.source = Some("https://example.com/a/very/long/source/url".into());
+        quote.rating = Some(4);
+        quote.page = Some(199);
+        // Tests run without a terminal
+        assert!(utils::terminal_width() >= config::MIN_TERMINAL_WIDTH);
+        for width in &[0, 1, 5, config::MIN_TERMINAL_WIDTH] {
+            let print_style = PrintStyle {
+                width: *width,
+                ..PrintStyle::default()
+            };
+            assert!(quote.to_pretty(&print_style).contains("Ursula K. Le Guin"));
+            assert!(quote.to_boxed(&print_style).contains("A note"));
+        }
+    }
+
+    fn record_indices(contents: &str) -> Vec<usize> {
+        let (_file, path) = json_file(contents);
+        Quote::read_json_records(&path)
+            .unwrap()
+            .map(|record| {
+                let value: serde_json::Value = serde_json::from_str(record.unwrap().get()).unwrap();
+                value["index"].as_u64().unwrap() as usize
+            })
+            .collect()
+    }
+
+    #[test]
+    fn json_records_from_an_array() {
+        assert_eq!(
+            record_indices(
+                r#"
+  [{"index": 1}, {"index": 2},
+{"index": 3}]"#
+            ),
+            vec![1, 2, 3]
+        );
+        assert!(record_indices("[]").is_empty());
+    }
+
+    #[test]
+    fn json_records_from_a_stream() {
+        assert_eq!(
+            record_indices(
+                r#"{"index": 1}
+{"index": 2} {"index": 3}"#
+            ),
+            vec![1, 2, 3]
+        );
+        assert!(record_indices("").is_empty());
     }
 }
@@ -0,0 +1,69 @@
+use anyhow::Error;
+
+use crate::errors::QuothError;
+
+/// Usage statistics, as printed by `quoth stats --json` and `quoth stats --plain`
+#[derive(Serialize, Debug)]
+pub struct Stats {
+    /// Quote and book counts for every month from the first to the last month with a quote
+    pub months: Vec<MonthCounts>,
+    /// Book and quote counts for every author (sorted by author unless `--sort-authors` is given)
+    pub authors: Vec<AuthorCounts>,
+    /// Totals over the whole database
+    pub totals: Totals,
+}
+
+/// Number of quotes and books recorded in a month (each book is counted in one month only)
+#[derive(Serialize, Debug)]
+pub struct MonthCounts {
+    /// Month as YYYY-MM
+    pub month: String,
+    pub quotes: u64,
+    pub books: u64,
+}
+
+/// Number of books and quotes by an author
+#[derive(Serialize, Debug)]
+pub struct AuthorCounts {
+    pub author: String,
+    pub books: u64,
+    pub quotes: u64,
+}
+
+impl AuthorCounts {
+    /// Sorts authors by name, or by number of quotes or books (most first, ties sorted by name)
+    pub fn sort_by_field(authors: &mut [AuthorCounts], field: &str) -> Result<(), Error> {
+        match field {
+            "name" => authors.sort_by(|a1, a2| a1.author.cmp(&a2.author)),
+            "quotes" => authors.sort_by(|a1, a2| {
+                a2.quotes
+                    .cmp(&a1.quotes)
+                    .then_with(|| a1.author.cmp(&a2.author))
+            }),
+            "books" => authors.sort_by(|a1, a2| {
+                a2.books
+                    .cmp(&a1.books)
+                    .then_with(|| a1.author.cmp(&a2.author))
+            }),
+            _ => {
+                return Err(QuothError::OutOfCheeseError {
+                    message: format!("Can't sort authors by {:?}", field),
+                }
+                .into())
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of quotes, books, authors, tags, and words (in quote texts) stored
+#[derive(Serialize, Debug)]
+pub struct Totals {
+    pub quotes: usize,
+    pub books: usize,
+    pub authors: usize,
+    pub tags: usize,
+    pub words: usize,
+    /// Average number of words per quote (0 if there are no quotes)
+    pub words_per_quote: f64,
+}
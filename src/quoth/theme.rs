@@ -0,0 +1,115 @@
+use console::{Attribute, Color, Style, StyledObject};
+
+/// A color and text attributes to print part of a quote with
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoleStyle {
+    /// Foreground color (the terminal's own if `None`)
+    pub color: Option<Color>,
+    pub attributes: Vec<Attribute>,
+}
+
+impl RoleStyle {
+    fn new(color: Option<Color>, attributes: &[Attribute]) -> Self {
+        RoleStyle {
+            color,
+            attributes: attributes.to_vec(),
+        }
+    }
+
+    /// Parses space-separated color and attribute names (e.g. `cyan italic`),
+    /// returning `None` if a name isn't known. An empty string is the terminal's plain text style.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut role_style = RoleStyle::default();
+        for word in value.split_whitespace() {
+            match word.to_ascii_lowercase().as_str() {
+                "black" => role_style.color = Some(Color::Black),
+                "red" => role_style.color = Some(Color::Red),
+                "green" => role_style.color = Some(Color::Green),
+                "yellow" => role_style.color = Some(Color::Yellow),
+                "blue" => role_style.color = Some(Color::Blue),
+                "magenta" => role_style.color = Some(Color::Magenta),
+                "cyan" => role_style.color = Some(Color::Cyan),
+                "white" => role_style.color = Some(Color::White),
+                "bold" => role_style.attributes.push(Attribute::Bold),
+                "dim" => role_style.attributes.push(Attribute::Dim),
+                "italic" => role_style.attributes.push(Attribute::Italic),
+                "underlined" => role_style.attributes.push(Attribute::Underlined),
+                _ => return None,
+            }
+        }
+        Some(role_style)
+    }
+
+    /// Equivalent `console` style
+    pub fn to_style(&self) -> Style {
+        let mut style = Style::new();
+        if let Some(color) = self.color {
+            style = style.fg(color);
+        }
+        for attribute in &self.attributes {
+            style = style.attr(*attribute);
+        }
+        style
+    }
+
+    /// Styles `value` for printing
+    pub fn apply<D>(&self, value: D) -> StyledObject<D> {
+        self.to_style().apply_to(value)
+    }
+}
+
+/// Styles for each part of a printed quote and of the `quoth stats` dashboard
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub author: RoleStyle,
+    pub book: RoleStyle,
+    pub tags: RoleStyle,
+    /// Quote text
+    pub quote: RoleStyle,
+    /// Bars of the monthly quote chart
+    pub chart: RoleStyle,
+}
+
+impl Theme {
+    /// Names of the parts of a quote that can be styled
+    pub const ROLES: [&'static str; 5] = ["author", "book", "tags", "quote", "chart"];
+
+    /// Built-in theme called `name`: "default" (blue authors, cyan books) or "mono" (no colors, only dim and italic text)
+    pub fn named(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "default" => Some(Theme::default()),
+            "mono" => Some(Theme {
+                author: RoleStyle::default(),
+                book: RoleStyle::new(None, &[Attribute::Italic]),
+                tags: RoleStyle::new(None, &[Attribute::Dim]),
+                quote: RoleStyle::default(),
+                chart: RoleStyle::default(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Style of the role called `name` (one of `Theme::ROLES`)
+    pub fn role_mut(&mut self, name: &str) -> Option<&mut RoleStyle> {
+        match name {
+            "author" => Some(&mut self.author),
+            "book" => Some(&mut self.book),
+            "tags" => Some(&mut self.tags),
+            "quote" => Some(&mut self.quote),
+            "chart" => Some(&mut self.chart),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            author: RoleStyle::new(Some(Color::Blue), &[]),
+            book: RoleStyle::new(Some(Color::Cyan), &[Attribute::Italic]),
+            tags: RoleStyle::new(None, &[Attribute::Dim]),
+            quote: RoleStyle::default(),
+            chart: RoleStyle::new(Some(Color::White), &[]),
+        }
+    }
+}
@@ -1,26 +1,27 @@
-use std::collections::HashMap;
-use std::io;
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
 use std::str;
-use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 
-use anyhow::Error;
-use chrono::{Date, Datelike, DateTime, MAX_DATE, MIN_DATE, Utc};
+use anyhow::{Context, Error};
+use chrono::{Date, Datelike, DateTime, Duration, MAX_DATE, MIN_DATE, TimeZone, Utc};
 use chrono_english::{Dialect, parse_date_string};
 use clap::ArgMatches;
+use console::{Alignment, Term};
 use csv;
-use dialoguer::{Editor, Input, theme};
-use path_abs::PathFile;
-use serde_json;
-use termion::event::Key;
-use termion::input::TermRead;
+use dialoguer::{Completion, Editor, Input, theme};
+use strsim::normalized_damerau_levenshtein;
+use textwrap::termwidth;
+use unicode_width::UnicodeWidthStr;
 
 use crate::config;
 use crate::errors::QuothError;
 
-/// ASCII code of semicolon
-pub const SEMICOLON: u8 = 59;
+/// Separates the values (indices, book titles, authors) stored under one key in the linkage trees
+/// (NUL, since unlike the semicolon used before it can't show up in a book title)
+pub const SEPARATOR: u8 = 0;
 
 pub const RAVEN: char = '\u{1313F}';
 
@@ -42,12 +43,150 @@ pub fn camel_case_phrase(input: &str) -> String {
         .join(" ")
 }
 
-/// Splits input by comma
-pub fn split_tags(input: &str) -> Vec<String> {
+/// Lowercases text and collapses whitespace, for comparing quotes
+pub fn normalize_text(input: &str) -> String {
     input
-        .split(',')
-        .map(|word| word.trim().to_string())
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
         .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Trims a tag and collapses the whitespace in it (keeping its case, see `normalize_tag` for comparing tags)
+pub fn clean_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cleans and lowercases a tag, so that "Fiction ", "fiction", and "FICTION" are the same tag
+/// (tags are compared and stored as `sled` keys in this form)
+pub fn normalize_tag(tag: &str) -> String {
+    clean_tag(tag).to_lowercase()
+}
+
+/// Cleans tags, skipping empty ones and ones that are the same tag as an earlier one (see `normalize_tag`)
+pub fn dedup_tags<I: IntoIterator<Item = String>>(tags: I) -> Vec<String> {
+    let mut deduped: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    for tag in tags {
+        let tag = clean_tag(&tag);
+        if !tag.is_empty() && seen.insert(normalize_tag(&tag)) {
+            deduped.push(tag);
+        }
+    }
+    deduped
+}
+
+/// Splits input by comma (cleaning each tag, skipping empty and repeated ones)
+pub fn split_tags(input: &str) -> Vec<String> {
+    dedup_tags(input.split(',').map(String::from))
+}
+
+/// Splits input by comma (camel-casing each author, skipping empty and repeated ones)
+pub fn split_authors(input: &str) -> Vec<String> {
+    let mut authors: Vec<String> = Vec::new();
+    for author in input.split(',').map(camel_case_phrase) {
+        if !author.is_empty() && !authors.contains(&author) {
+            authors.push(author);
+        }
+    }
+    authors
+}
+
+/// Splits text into its distinct lowercased words (runs of letters and digits)
+pub fn split_words(input: &str) -> Vec<String> {
+    let mut words: Vec<String> = input
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// Finds the candidate most similar to the input (ignoring case) if it's at least `threshold` similar (0 to 1),
+/// or None if there's no such candidate or one matches the input exactly
+pub fn closest_match<'a>(input: &str, candidates: &'a [String], threshold: f64) -> Option<&'a str> {
+    let input = input.to_lowercase();
+    let mut best: Option<(&str, f64)> = None;
+    for candidate in candidates {
+        let similarity = normalized_damerau_levenshtein(&input, &candidate.to_lowercase());
+        if similarity >= 1. {
+            return None;
+        }
+        if similarity >= threshold && best.is_none_or(|(_, s)| similarity > s) {
+            best = Some((candidate, similarity));
+        }
+    }
+    best.map(|(candidate, _)| candidate)
+}
+
+/// Escapes characters with a special meaning in TeX
+pub fn escape_tex(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes characters with a special meaning in XML text and attribute values
+pub fn escape_xml(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes characters with a special meaning in iCalendar text values
+pub fn escape_ical(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Folds an iCalendar content line into lines of at most 75 bytes (continuation lines start with a space),
+/// each ending in CRLF
+pub fn fold_ical_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 3);
+    let mut line_length = 0;
+    for c in line.chars() {
+        if line_length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            line_length = 1;
+        }
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
 }
 
 /// Converts an array of bytes to a string
@@ -55,53 +194,203 @@ pub fn u8_to_str(input: &[u8]) -> Result<String, Error> {
     Ok(str::from_utf8(input)?.to_owned())
 }
 
-/// Splits byte array by semicolon into strings
+/// Splits byte array by `SEPARATOR` into strings (skipping empty ones)
 pub fn split_values_string(index_list: &[u8]) -> Result<Vec<String>, Error> {
     let index_list_string = str::from_utf8(index_list)?;
     Ok(index_list_string
-        .split(str::from_utf8(&[SEMICOLON])?)
+        .split(str::from_utf8(&[SEPARATOR])?)
+        .filter(|s| !s.is_empty())
         .map(|s| s.to_string())
         .collect())
 }
 
-/// Splits byte array by semicolon into usize
+/// Splits byte array by `SEPARATOR` into usize (an empty array gives no indices)
 pub fn split_indices_usize(index_list: &[u8]) -> Result<Vec<usize>, Error> {
     let index_list_string = str::from_utf8(index_list)?;
     Ok(index_list_string
-        .split(str::from_utf8(&[SEMICOLON])?)
+        .split(str::from_utf8(&[SEPARATOR])?)
+        .filter(|word| !word.is_empty())
         .map(|word: &str| word.parse::<usize>())
         .collect::<Result<Vec<_>, _>>()?)
 }
 
-/// List of usize into semicolon-joined byte array
+/// List of usize into `SEPARATOR`-joined byte array (empty for an empty list)
 pub fn make_indices_string(index_list: &[usize]) -> Result<Vec<u8>, Error> {
     Ok(index_list
         .iter()
         .map(|index| index.to_string())
         .collect::<Vec<String>>()
-        .join(str::from_utf8(&[SEMICOLON])?)
+        .join(str::from_utf8(&[SEPARATOR])?)
         .as_bytes()
         .to_vec())
 }
 
+/// Parses a date like "2020-01-31", "31/01/2020", "today", "yesterday", "last monday", or "3 days ago"
 pub fn parse_date(date_string: &str) -> Result<Date<Utc>, Error> {
-    if date_string.to_ascii_lowercase() == "today" {
-        Ok(Utc::now().date())
+    let lowercase = date_string.trim().to_lowercase();
+    let now = Utc::now();
+    match lowercase.as_str() {
+        "today" => Ok(now.date()),
+        "yesterday" => Ok(now.date() - Duration::days(1)),
+        _ => {
+            // chrono-english wants a number in "a week ago"
+            let lowercase = match lowercase.splitn(2, ' ').collect::<Vec<_>>().as_slice() {
+                ["a", rest] | ["an", rest] if rest.ends_with(" ago") => format!("1 {}", rest),
+                _ => lowercase.clone(),
+            };
+            Ok(parse_date_string(&lowercase, now, Dialect::Uk)
+                .map_err(|_| QuothError::DateParseError {
+                    date: date_string.to_owned(),
+                })?
+                .date())
+        }
+    }
+}
+
+/// Parses an optional page number ("" => None)
+pub fn parse_page(page_string: &str) -> Result<Option<u32>, Error> {
+    if page_string.trim().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(page_string.trim().parse::<u32>().with_context(
+            || format!("Page {:?} is not a number", page_string),
+        )?))
+    }
+}
+
+/// Parses quote indices separated by commas or whitespace, with "3..8" for an (inclusive) range of indices
+pub fn parse_indices(indices_string: &str) -> Result<Vec<usize>, Error> {
+    let parse_index = |index: &str| {
+        index
+            .parse::<usize>()
+            .with_context(|| format!("Given index {:?} is not a number", index))
+    };
+    let mut indices = Vec::new();
+    for part in indices_string
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+    {
+        match part.find("..") {
+            Some(i) => {
+                let (start, end) = (parse_index(&part[..i])?, parse_index(&part[i + 2..])?);
+                if start > end {
+                    return Err(QuothError::BadIndexRange {
+                        range: part.to_owned(),
+                    }
+                    .into());
+                }
+                indices.extend(start..=end);
+            }
+            None => indices.push(parse_index(part)?),
+        }
+    }
+    Ok(indices)
+}
+
+/// Parses an optional rating from 1 to 5 ("" => None)
+pub fn parse_rating(rating_string: &str) -> Result<Option<u8>, Error> {
+    let rating_string = rating_string.trim();
+    if rating_string.is_empty() {
+        return Ok(None);
+    }
+    match rating_string.parse::<u8>() {
+        Ok(rating) if (1..=5).contains(&rating) => Ok(Some(rating)),
+        _ => Err(QuothError::BadRating {
+            rating: rating_string.to_owned(),
+        }
+        .into()),
+    }
+}
+
+/// Parses an optional http(s) source URL ("" => None)
+pub fn parse_source(source_string: &str) -> Result<Option<String>, Error> {
+    let source_string = source_string.trim();
+    if source_string.is_empty() {
+        Ok(None)
+    } else if (source_string.starts_with("http://") || source_string.starts_with("https://"))
+        && !source_string.contains(char::is_whitespace)
+    {
+        Ok(Some(source_string.to_owned()))
+    } else {
+        Err(QuothError::BadUrl {
+            url: source_string.to_owned(),
+        }
+        .into())
+    }
+}
+
+/// Checks that chrono can format dates with a given format string
+/// (by formatting a sample date, which also catches specifiers dates don't have, like %H or %M)
+pub fn check_date_format(date_format: &str) -> Result<(), Error> {
+    let mut sample = String::new();
+    write!(sample, "{}", Utc::today().format(date_format)).map_err(|_| {
+        QuothError::DateFormatError {
+            format: date_format.to_owned(),
+        }
+    })?;
+    Ok(())
+}
+
+/// Average number of words per quote (0 if there are no quotes)
+pub fn words_per_quote(num_words: usize, num_quotes: usize) -> f64 {
+    if num_quotes == 0 {
+        0.
     } else {
-        Ok(parse_date_string(date_string, Utc::now(), Dialect::Uk)?.date())
+        num_words as f64 / num_quotes as f64
     }
 }
 
+/// Pads each line of `text` with spaces to `width` terminal columns
+/// (measured with `unicode-width`, so CJK characters and emoji count as two columns; longer lines are left alone)
+pub fn pad_lines(text: &str, width: usize, alignment: Alignment) -> String {
+    text.split('\n')
+        .map(|line| {
+            let padding = width.saturating_sub(UnicodeWidthStr::width(line));
+            let left = match alignment {
+                Alignment::Left => 0,
+                Alignment::Center => padding / 2,
+                Alignment::Right => padding,
+            };
+            format!("{}{}{}", " ".repeat(left), line, " ".repeat(padding - left))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Width of the terminal, but at least `config::MIN_TERMINAL_WIDTH`
+pub fn terminal_width() -> usize {
+    termwidth().max(config::MIN_TERMINAL_WIDTH)
+}
+
 /// Some(date) => date
 /// None => minimum possible date
 pub fn date_start(from_date: Option<DateTime<Utc>>) -> DateTime<Utc> {
     from_date.unwrap_or_else(|| MIN_DATE.and_hms(0, 0, 0))
 }
 
+/// The same day `months` months before `date` (or the last day of that month if it's shorter)
+pub fn months_before(date: Date<Utc>, months: u32) -> Date<Utc> {
+    let month_count = date.year() * 12 + date.month0() as i32 - months as i32;
+    let (year, month) = (
+        month_count.div_euclid(12),
+        month_count.rem_euclid(12) as u32 + 1,
+    );
+    (1..=date.day())
+        .rev()
+        .filter_map(|day| Utc.ymd_opt(year, month, day).single())
+        .next()
+        .unwrap_or(date)
+}
+
+/// Last instant of a day (so quotes recorded at 23:59:59.5 still count as on that day)
+pub fn end_of_day(date: Date<Utc>) -> DateTime<Utc> {
+    date.and_hms_nano(23, 59, 59, 999_999_999)
+}
+
 /// Some(date) => date
 /// None => maximum possible date
 pub fn date_end(to_date: Option<DateTime<Utc>>) -> DateTime<Utc> {
-    to_date.unwrap_or_else(|| MAX_DATE.and_hms(23, 59, 59))
+    to_date.unwrap_or_else(|| end_of_day(MAX_DATE))
 }
 
 /// Gets input from external editor, optionally displays default text in editor
@@ -112,28 +401,109 @@ pub fn external_editor_input(default: Option<&str>) -> Result<String, Error> {
     }
 }
 
+/// Shows `text` through $PAGER (`config::PAGER_DEFAULT` if unset) if `use_pager`,
+/// printing it directly instead if not or if the pager can't be started
+pub fn print_paged(text: &str, use_pager: bool) -> Result<(), Error> {
+    if use_pager {
+        let pager = env::var("PAGER").unwrap_or_else(|_| config::PAGER_DEFAULT.into());
+        let mut pager_words = pager.split_whitespace();
+        if let Some(program) = pager_words.next() {
+            let mut command = Command::new(program);
+            command.args(pager_words).stdin(Stdio::piped());
+            // Like git: less quits straight away if the text fits on one screen, and shows colors
+            if env::var_os("LESS").is_none() {
+                command.env("LESS", "FRX");
+            }
+            if let Ok(mut child) = command.spawn() {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // The pager stops reading if it's quit before the end
+                    match stdin.write_all(text.as_bytes()) {
+                        Err(ref error) if error.kind() == io::ErrorKind::BrokenPipe => (),
+                        result => result?,
+                    }
+                }
+                child.wait()?;
+                return Ok(());
+            }
+        }
+    }
+    print!("{}", text);
+    Ok(())
+}
+
+/// Completes the text typed into a prompt (on Tab or the right arrow key) with one of a list of known values
+pub struct Completer<'a> {
+    pub values: &'a [String],
+    /// Only completes the text after the last separator (e.g. ',' for the tags prompt) if given
+    pub separator: Option<char>,
+}
+
+impl<'a> Completion for Completer<'a> {
+    /// Completes to the longest common start of the values starting with the typed text (ignoring case)
+    fn get(&self, input: &str) -> Option<String> {
+        let (head, partial) = match self.separator.and_then(|separator| input.rfind(separator)) {
+            Some(position) => input.split_at(position + 1),
+            None => ("", input),
+        };
+        let word = partial.trim_start();
+        if word.is_empty() {
+            return None;
+        }
+        let word = word.to_lowercase();
+        let mut matches = self
+            .values
+            .iter()
+            .filter(|value| value.to_lowercase().starts_with(&word));
+        let first = matches.next()?;
+        let completion = matches.fold(first.to_owned(), |common, value| {
+            common
+                .chars()
+                .zip(value.chars())
+                .take_while(|(c1, c2)| c1.to_lowercase().eq(c2.to_lowercase()))
+                .map(|(c, _)| c)
+                .collect()
+        });
+        Some(format!(
+            "{}{}{}",
+            head,
+            &partial[..partial.len() - partial.trim_start().len()],
+            completion
+        ))
+    }
+}
+
 /// Takes user input from terminal, optionally has a default and optionally displays it.
 pub fn user_input(
     message: &str,
     default: Option<&str>,
     show_default: bool,
 ) -> Result<String, Error> {
-    match default {
-        Some(default) => Ok(Input::with_theme(&theme::ColorfulTheme::default())
-            .with_prompt(message)
-            .default(default.to_owned())
-            .show_default(show_default)
-            .interact()?
-            .trim()
-            .to_owned()),
-        None => Ok(
-            Input::<String>::with_theme(&theme::ColorfulTheme::default())
-                .with_prompt(message)
-                .interact()?
-                .trim()
-                .to_owned(),
-        ),
+    completed_user_input(message, default, show_default, None)
+}
+
+/// Like `user_input`, but the input can be completed with `completer`
+pub fn completed_user_input(
+    message: &str,
+    default: Option<&str>,
+    show_default: bool,
+    completer: Option<&Completer<'_>>,
+) -> Result<String, Error> {
+    let theme = theme::ColorfulTheme::default();
+    let mut input = Input::<String>::with_theme(&theme);
+    input.with_prompt(message);
+    if let Some(default) = default {
+        input.default(default.to_owned()).show_default(show_default);
     }
+    if let Some(completer) = completer {
+        input.completion_with(completer);
+    }
+    // Without a terminal, interact_text gives up straight away instead of reading a line (or using the default)
+    let input = if Term::stderr().is_term() {
+        input.interact_text()?
+    } else {
+        input.interact()?
+    };
+    Ok(input.trim().to_owned())
 }
 
 /// Extracts value of a given argument from matches if present
@@ -153,6 +523,25 @@ pub fn get_argument_value<'a>(
     }
 }
 
+/// Extracts all values of a given (multiple-occurrence) argument from matches
+pub fn get_argument_values<'a>(
+    name: &str,
+    matches: &'a ArgMatches<'a>,
+) -> Result<Vec<&'a str>, Error> {
+    match matches.values_of(name) {
+        Some(values) => values
+            .map(|value| {
+                if value.trim().is_empty() {
+                    Err(QuothError::NoInputError.into())
+                } else {
+                    Ok(value.trim())
+                }
+            })
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
 /// Sorts array using insertion sort (good for almost sorted arrays)
 pub fn insertion_sort(array: &[usize]) -> Vec<usize> {
     let mut output_array = array.to_vec().clone();
@@ -171,129 +560,41 @@ pub fn insertion_sort(array: &[usize]) -> Vec<usize> {
     output_array
 }
 
+/// Lists the first day of every month from `min_date` to `max_date` (inclusive)
 pub fn get_months(min_date: Date<Utc>, max_date: Date<Utc>) -> Vec<Date<Utc>> {
-    let (min_year, min_month) = (min_date.year(), min_date.month());
+    let (mut year, mut month) = (min_date.year(), min_date.month());
     let (max_year, max_month) = (max_date.year(), max_date.month());
-    let mut months = Vec::with_capacity((max_year - min_year) as usize * 12);
-    let date = Utc::now().date();
-    for month in min_month..=12 {
-        months.push(
-            date.with_year(min_year)
-                .unwrap()
-                .with_month(month)
-                .unwrap()
-                .with_day(1)
-                .unwrap(),
-        );
-    }
-    for year in min_year..max_year {
-        for month in 1..=12 {
-            months.push(
-                date.with_year(year)
-                    .unwrap()
-                    .with_month(month)
-                    .unwrap()
-                    .with_day(1)
-                    .unwrap(),
-            );
+    let mut months = Vec::new();
+    while (year, month) <= (max_year, max_month) {
+        months.push(Utc.ymd(year, month, 1));
+        if month == 12 {
+            year += 1;
+            month = 1;
+        } else {
+            month += 1;
         }
     }
-    for month in 1..=max_month {
-        months.push(
-            date.with_year(max_year)
-                .unwrap()
-                .with_month(month)
-                .unwrap()
-                .with_day(1)
-                .unwrap(),
-        );
-    }
     months
 }
 
-pub enum Event<I> {
-    Input(I),
-    Tick,
-}
-
-/// A small event handler that wraps termion input and tick events. Each event
-/// type is handled in its own thread and returned to a common `Receiver`
-pub struct Events {
-    rx: mpsc::Receiver<Event<Key>>,
-    input_handle: thread::JoinHandle<()>,
-    tick_handle: thread::JoinHandle<()>,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct Config {
-    pub exit_key: Key,
-    pub tick_rate: Duration,
-}
-
-impl Default for Config {
-    fn default() -> Config {
-        Config {
-            exit_key: Key::Char('q'),
-            tick_rate: Duration::from_millis(250),
-        }
-    }
-}
-
-impl Events {
-    pub fn new() -> Events {
-        Events::with_config(Config::default())
-    }
-
-    pub fn with_config(config: Config) -> Events {
-        let (tx, rx) = mpsc::channel();
-        let input_handle = {
-            let tx = tx.clone();
-            thread::spawn(move || {
-                let stdin = io::stdin();
-                for evt in stdin.keys() {
-                    if let Ok(key) = evt {
-                        if tx.send(Event::Input(key)).is_err() {
-                            return;
-                        }
-                        if key == config.exit_key {
-                            return;
-                        }
-                    }
-                }
-            })
-        };
-        let tick_handle = {
-            let tx = tx.clone();
-            thread::spawn(move || {
-                let tx = tx.clone();
-                loop {
-                    tx.send(Event::Tick).unwrap();
-                    thread::sleep(config.tick_rate);
-                }
-            })
-        };
-        Events {
-            rx,
-            input_handle,
-            tick_handle,
-        }
-    }
-
-    pub fn next(&self) -> Result<Event<Key>, mpsc::RecvError> {
-        self.rx.recv()
-    }
+/// A quote from the Quotes-500K dataset (see `read_quotes_database`)
+#[derive(Serialize, Debug)]
+pub struct DatasetQuote {
+    pub quote: String,
+    pub book: String,
+    /// Comma-separated categories
+    pub tags: String,
 }
 
-/// Reads quote database (downloaded from https://github.com/ShivaliGoel/Quotes-500K) and saves it as
-/// a JSON file of authors mapped to all their quotes.
+/// Reads quote database (downloaded from https://github.com/ShivaliGoel/Quotes-500K) into
+/// authors mapped to all their quotes (sorted by author, quotes in the order they're in the file).
 pub fn read_quotes_database(
     full_database_file: &str,
-    output_database_file: &str,
-) -> Result<(), Error> {
+) -> Result<BTreeMap<String, Vec<DatasetQuote>>, Error> {
     let mut reader = csv::ReaderBuilder::new()
         .delimiter(b',')
         .from_path(&full_database_file)?;
-    let mut quote_db = HashMap::new();
+    let mut quote_db = BTreeMap::new();
     for result in reader.records() {
         let record = result?;
         let quote = record.get(0);
@@ -303,13 +604,146 @@ pub fn read_quotes_database(
             // Filters out book-less quotes
             if author_book.len() >= 2 {
                 quote_db
-                    .entry(author_book[0].to_owned())
+                    .entry(author_book[0].trim().to_owned())
                     .or_insert_with(Vec::new)
-                    .push(quote.to_owned());
+                    .push(DatasetQuote {
+                        quote: quote.to_owned(),
+                        book: author_book[1..].join(",").trim().to_owned(),
+                        tags: record.get(2).unwrap_or("").to_owned(),
+                    });
             }
         }
     }
-    let output_database_file = PathFile::create(output_database_file)?;
-    output_database_file.write_str(&serde_json::to_string(&quote_db)?)?;
-    Ok(())
+    Ok(quote_db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn month_strings(min_date: Date<Utc>, max_date: Date<Utc>) -> Vec<String> {
+        get_months(min_date, max_date)
+            .into_iter()
+            .map(|month| month.format("%Y-%m").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn months_in_the_same_year() {
+        assert_eq!(
+            month_strings(Utc.ymd(2019, 3, 15), Utc.ymd(2019, 5, 2)),
+            vec!["2019-03", "2019-04", "2019-05"]
+        );
+        assert_eq!(
+            month_strings(Utc.ymd(2019, 3, 15), Utc.ymd(2019, 3, 20)),
+            vec!["2019-03"]
+        );
+    }
+
+    #[test]
+    fn months_across_adjacent_years() {
+        assert_eq!(
+            month_strings(Utc.ymd(2018, 11, 30), Utc.ymd(2019, 2, 1)),
+            vec!["2018-11", "2018-12", "2019-01", "2019-02"]
+        );
+    }
+
+    #[test]
+    fn months_across_several_years() {
+        let months = month_strings(Utc.ymd(2016, 6, 1), Utc.ymd(2019, 1, 31));
+        assert_eq!(months.len(), 32);
+        assert_eq!(months.first().unwrap(), "2016-06");
+        assert!(months.contains(&"2017-12".to_owned()));
+        assert_eq!(months.last().unwrap(), "2019-01");
+    }
+
+    #[test]
+    fn parse_date_keywords() {
+        let today = Utc::now().date();
+        assert_eq!(parse_date("today").unwrap(), today);
+        assert_eq!(
+            parse_date(" Yesterday ").unwrap(),
+            today - Duration::days(1)
+        );
+        assert_eq!(parse_date("3 days ago").unwrap(), today - Duration::days(3));
+        assert_eq!(parse_date("a week ago").unwrap(), today - Duration::days(7));
+        assert_eq!(parse_date("2020-01-31").unwrap(), Utc.ymd(2020, 1, 31));
+    }
+
+    #[test]
+    fn parse_date_rejects_nonsense() {
+        let error = parse_date("the day after never").unwrap_err();
+        match error.downcast_ref::<QuothError>() {
+            Some(QuothError::DateParseError { date }) => assert_eq!(date, "the day after never"),
+            _ => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn split_and_make_empty_lists() {
+        assert!(split_indices_usize(b"").unwrap().is_empty());
+        assert!(split_values_string(b"").unwrap().is_empty());
+        assert!(make_indices_string(&[]).unwrap().is_empty());
+        assert!(split_tags("").is_empty());
+        assert!(split_tags(" , ").is_empty());
+        assert!(split_authors("").is_empty());
+        assert!(split_words("").is_empty());
+    }
+
+    #[test]
+    fn split_and_make_single_elements() {
+        assert_eq!(split_indices_usize(b"42").unwrap(), vec![42]);
+        assert_eq!(split_values_string(b"Tehanu").unwrap(), vec!["Tehanu"]);
+        assert_eq!(make_indices_string(&[42]).unwrap(), b"42".to_vec());
+        assert_eq!(split_tags("fiction"), vec!["fiction"]);
+        assert_eq!(split_authors("le guin"), vec!["Le Guin"]);
+        assert_eq!(split_words("Tehanu"), vec!["tehanu"]);
+    }
+
+    #[test]
+    fn make_and_split_indices_round_trip() {
+        let indices = make_indices_string(&[1, 20, 300]).unwrap();
+        assert_eq!(split_indices_usize(&indices).unwrap(), vec![1, 20, 300]);
+    }
+
+    #[test]
+    fn pad_lines_counts_display_width() {
+        // Four CJK characters: 12 bytes, 8 columns
+        let text = "左手暗闇\nhand";
+        for alignment in &[Alignment::Left, Alignment::Center, Alignment::Right] {
+            let padded = pad_lines(text, 12, *alignment);
+            for line in padded.split('\n') {
+                assert_eq!(UnicodeWidthStr::width(line), 12);
+            }
+        }
+        assert_eq!(pad_lines("左手暗闇", 12, Alignment::Center), "  左手暗闇  ");
+        // Lines wider than `width` are left alone
+        assert_eq!(pad_lines("左手暗闇", 5, Alignment::Right), "左手暗闇");
+    }
+
+    #[test]
+    fn date_formats_without_times_are_accepted() {
+        assert!(check_date_format("%Y-%m-%d").is_ok());
+        assert!(check_date_format("%d %B %Y").is_ok());
+    }
+
+    #[test]
+    fn date_formats_dates_cant_fill_are_rejected() {
+        for date_format in &["%Y %H:%M", "%d %B %Y, %I%p", "%Q"] {
+            let error = check_date_format(date_format).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<QuothError>(),
+                Some(QuothError::DateFormatError { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn split_tags_keeps_case_but_drops_repeats() {
+        assert_eq!(
+            split_tags(" Science  Fiction, science fiction,fantasy ,, FANTASY"),
+            vec!["Science Fiction", "fantasy"]
+        );
+        assert_eq!(normalize_tag(" Science  Fiction "), "science fiction");
+    }
 }
@@ -0,0 +1,74 @@
+//! Runs the quoth binary against a quoth directory in a fresh $HOME
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use serde_json::{json, Value};
+
+/// quoth with `args`, using `home` as $HOME (and nothing on stdin, so prompts take their defaults)
+fn quoth(home: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_quoth"));
+    command
+        .args(args)
+        .env("HOME", home)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("CLICOLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .stdin(Stdio::null());
+    command
+}
+
+/// Runs `command`, checking that it worked, and returns what it printed
+fn run(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn add_quote(home: &Path, book: &str, quote: &str) {
+    run(&mut quoth(
+        home,
+        &["add", "-a", "Ursula K. Le Guin", "-b", book, "-q", quote],
+    ));
+}
+
+fn show_json(home: &Path, indices: &str) -> Vec<Value> {
+    run(&mut quoth(home, &["--show", indices, "--json"]))
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn changing_a_quote_keeps_its_links() {
+    let home = tempfile::tempdir().unwrap();
+    add_quote(home.path(), "The Dispossessed", "True journey is return.");
+    add_quote(home.path(), "The Lathe Of Heaven", "Self goes beyond self.");
+    run(&mut quoth(home.path(), &["link", "1", "2"]));
+    // The "editor" replaces the quote text, every other prompt keeps its default
+    run(quoth(home.path(), &["--change", "1"]).env("VISUAL", "sh -c 'echo Changed. > \"$0\"'"));
+    let quotes = show_json(home.path(), "1,2");
+    assert_eq!(quotes[0]["quote"], "Changed.");
+    assert_eq!(quotes[0]["related"], json!([2]));
+    assert_eq!(quotes[1]["related"], json!([1]));
+}
+
+#[test]
+fn piped_and_uncolored_output_has_no_escape_codes() {
+    let home = tempfile::tempdir().unwrap();
+    add_quote(home.path(), "The Dispossessed", "True journey is return.");
+    for show in &[vec!["--show", "1"], vec!["--show", "1", "--box"]] {
+        let show_uncolored = [&show[..], &["--no-color"]].concat();
+        // Output is piped here, so console leaves out colors unless they're forced
+        assert!(!run(&mut quoth(home.path(), show)).contains('\u{1b}'));
+        assert!(run(quoth(home.path(), show).env("CLICOLOR_FORCE", "1")).contains('\u{1b}'));
+        assert!(
+            !run(quoth(home.path(), &show_uncolored).env("CLICOLOR_FORCE", "1")).contains('\u{1b}')
+        );
+    }
+}